@@ -0,0 +1,222 @@
+use std::fmt;
+use std::ops::Range;
+
+/// Markdown comment `<!-- ... -->` as a byte range including the delimiters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Comment {
+    pub begin: usize,
+    pub end: usize,
+}
+
+/// Markdown comment state of a line
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommentLine {
+    /// Byte range of the commented text without the delimiters and the padding spaces
+    pub original: Option<Range<usize>>,
+    /// Whether the line has text outside comments
+    pub translated: bool,
+    /// Whether the line overlaps a comment including the delimiters
+    pub commented: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommentErrorKind {
+    /// `<!--` without the following `-->`
+    Unterminated,
+    /// `-->` at the beginning of a line without the preceding `<!--`
+    Misordered,
+}
+
+#[derive(Clone, Debug)]
+pub struct CommentError {
+    pub kind: CommentErrorKind,
+    pub line: usize,
+    pub column: usize,
+    pub content: String,
+}
+
+impl fmt::Display for CommentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self.kind {
+            CommentErrorKind::Unterminated => "'<!--' is not closed",
+            CommentErrorKind::Misordered => "'-->' is found without '<!--'",
+        };
+        write!(
+            f,
+            "Failed to parse markdown comment: {} at {}:{}",
+            message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for CommentError {}
+
+/// Scan markdown comments in `text`
+pub fn scan(text: &str) -> Result<Vec<Comment>, CommentError> {
+    let mut ret = Vec::new();
+    let mut pos = 0;
+    while pos < text.len() {
+        let begin = text[pos..].find("<!--").map(|x| pos + x);
+
+        // Closing delimiters before the opening one are stray
+        let stray_end = match begin {
+            Some(begin) => &text[pos..begin],
+            None => &text[pos..],
+        };
+        let stray_end = stray_end
+            .match_indices("-->")
+            .map(|(x, _)| pos + x)
+            .find(|x| {
+                let line_begin = text[..*x].rfind('\n').map(|x| x + 1).unwrap_or(0);
+                text[line_begin..*x].trim().is_empty()
+            });
+        if let Some(x) = stray_end {
+            return Err(error(text, x, CommentErrorKind::Misordered));
+        }
+
+        let begin = match begin {
+            Some(x) => x,
+            None => break,
+        };
+        let end = match text[begin + 4..].find("-->") {
+            Some(x) => begin + 4 + x + 3,
+            None => return Err(error(text, begin, CommentErrorKind::Unterminated)),
+        };
+        ret.push(Comment { begin, end });
+        pos = end;
+    }
+    Ok(ret)
+}
+
+/// Track markdown comments of each line in `text`
+///
+/// The comments can be placed in the middle of lines like `りんご <!-- Apple -->`.
+/// Malformed comments are ignored because they are reported by `scan` beforehand.
+pub fn track(text: &str) -> Vec<CommentLine> {
+    let comments = scan(text).unwrap_or_default();
+    let mut comments = comments.iter().peekable();
+
+    let mut ret = Vec::new();
+    let mut begin = 0;
+    for line in text.lines() {
+        let end = begin + line.len();
+        while comments.peek().is_some_and(|x| x.end <= begin) {
+            comments.next();
+        }
+
+        let mut tracked = CommentLine::default();
+        let mut outside = begin;
+        for x in comments.clone().take_while(|x| x.begin < end) {
+            tracked.commented = true;
+            if text[outside..x.begin.max(outside)].trim() != "" {
+                tracked.translated = true;
+            }
+            outside = outside.max(x.end);
+
+            let (inner_begin, inner_end) = (x.begin + 4, x.end - 3);
+            if tracked.original.is_some() {
+                continue;
+            }
+            if inner_begin <= begin && end <= inner_end {
+                tracked.original = Some(0..line.len());
+                continue;
+            }
+            let mut b = inner_begin.max(begin);
+            let mut e = inner_end.min(end);
+            if b == inner_begin && text[b..].starts_with(' ') {
+                b += 1;
+            }
+            if e == inner_end && e > b && text[..e].ends_with(' ') {
+                e -= 1;
+            }
+            if b < e && !text[b..e].trim().is_empty() {
+                tracked.original = Some(b - begin..e - begin);
+            }
+        }
+        if outside < end && !text[outside..end].trim().is_empty() {
+            tracked.translated = true;
+        }
+        ret.push(tracked);
+
+        begin = text[begin..]
+            .find('\n')
+            .map(|x| begin + x + 1)
+            .unwrap_or(text.len());
+    }
+    ret
+}
+
+fn error(text: &str, pos: usize, kind: CommentErrorKind) -> CommentError {
+    let line_begin = text[..pos].rfind('\n').map(|x| x + 1).unwrap_or(0);
+    let line_end = text[pos..]
+        .find('\n')
+        .map(|x| pos + x)
+        .unwrap_or(text.len());
+    CommentError {
+        kind,
+        line: text[..pos].matches('\n').count() + 1,
+        column: text[line_begin..pos].chars().count() + 1,
+        content: String::from(text[line_begin..line_end].trim_end_matches('\r')),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let text = "a <!-- b -->\n<!--\nc --> d --> e\n";
+        let ret = scan(text).unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(&text[ret[0].begin..ret[0].end], "<!-- b -->");
+        assert_eq!(&text[ret[1].begin..ret[1].end], "<!--\nc -->");
+
+        let text = "a --> b\n-->\n<!--\n";
+        let ret = scan(text).unwrap_err();
+        assert_eq!(ret.kind, CommentErrorKind::Misordered);
+        assert_eq!((ret.line, ret.column), (2, 1));
+
+        let text = "a --> b\n\nあ <!--\n";
+        let ret = scan(text).unwrap_err();
+        assert_eq!(ret.kind, CommentErrorKind::Unterminated);
+        assert_eq!((ret.line, ret.column), (3, 3));
+        assert_eq!(ret.content, "あ <!--");
+    }
+
+    #[test]
+    fn test_track() {
+        let text = "<!--\nApple\n\n-->\n<!-- Orange\nPeach -->\nりんご <!-- Lemon -->\nもも <!--\n--> みかん\nぶどう\n";
+        let ret = track(text);
+        let original: Vec<_> = text
+            .lines()
+            .zip(&ret)
+            .map(|(x, y)| y.original.clone().map(|r| &x[r]))
+            .collect();
+        assert_eq!(
+            original,
+            vec![
+                None,
+                Some("Apple"),
+                Some(""),
+                None,
+                Some("Orange"),
+                Some("Peach"),
+                Some("Lemon"),
+                None,
+                None,
+                None
+            ]
+        );
+        let translated: Vec<_> = ret.iter().map(|x| x.translated).collect();
+        assert_eq!(
+            translated,
+            vec![false, false, false, false, false, false, true, true, true, true]
+        );
+        let commented: Vec<_> = ret.iter().map(|x| x.commented).collect();
+        assert_eq!(
+            commented,
+            vec![true, true, true, true, true, true, true, true, true, false]
+        );
+    }
+}
@@ -1,4 +1,8 @@
+use crate::matcher::LineContext;
+use crate::syntax::CommentSyntax;
+use anyhow::{bail, Error};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 fn default_code_comment_header() -> String {
@@ -17,7 +21,48 @@ fn default_similar_threshold() -> f64 {
     0.5
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+fn default_rule_contexts() -> Vec<LineContext> {
+    vec![LineContext::Text]
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_untranslated_threshold() -> f64 {
+    0.5
+}
+
+/// Ids of built-in rules which can be used in `[severity]` and suppression directives
+pub const RULE_IDS: &[&str] = &[
+    "modified",
+    "missing",
+    "garbage",
+    "missing-file",
+    "malformed-comment",
+    "emphasis",
+    "half-paren",
+    "full-paren",
+    "glossary",
+    "code-span",
+    "url",
+    "heading",
+    "heading-anchor",
+    "link-target",
+    "placeholder",
+    "number",
+    "code-block",
+    "structure",
+    "untranslated",
+    "cjk-space",
+    "punctuation-width",
+    "quote-style",
+    "full-width-alnum",
+    "comment",
+    "trailing-whitespace",
+];
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(default)]
@@ -26,15 +71,32 @@ pub struct Config {
     pub matcher: ConfigMatcher,
     #[serde(default)]
     pub linter: ConfigLinter,
+    #[serde(default)]
+    pub source_linter: ConfigSourceLinter,
+    #[serde(default)]
+    pub severity: BTreeMap<String, Severity>,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Config {
-            excludes: Vec::new(),
-            matcher: ConfigMatcher::default(),
-            linter: ConfigLinter::default(),
+impl Config {
+    /// Check the keys of `[severity]` which can't be checked by `deny_unknown_fields`
+    pub fn validate(&self) -> Result<(), Error> {
+        let custom: Vec<_> = self
+            .linter
+            .rules
+            .iter()
+            .chain(&self.source_linter.rules)
+            .map(|x| x.id.as_str())
+            .collect();
+        for id in self.severity.keys() {
+            if !RULE_IDS.contains(&id.as_str()) && !custom.contains(&id.as_str()) {
+                bail!(
+                    "unknown rule id `{}` in [severity], expected one of `{}` or custom rule ids",
+                    id,
+                    RULE_IDS.join("`, `")
+                );
+            }
         }
+        Ok(())
     }
 }
 
@@ -53,6 +115,8 @@ pub struct ConfigMatcher {
     pub markdown_comment_end: String,
     #[serde(default = "default_similar_threshold")]
     pub similar_threshold: f64,
+    #[serde(default)]
+    pub code_comment_syntax: BTreeMap<String, CommentSyntax>,
 }
 
 impl Default for ConfigMatcher {
@@ -64,6 +128,7 @@ impl Default for ConfigMatcher {
             markdown_comment_begin: String::from("((("),
             markdown_comment_end: String::from(")))"),
             similar_threshold: 0.5,
+            code_comment_syntax: BTreeMap::new(),
         }
     }
 }
@@ -71,20 +136,165 @@ impl Default for ConfigMatcher {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct ConfigLinter {
-    #[serde(default)]
+    #[serde(default = "default_true")]
     pub enable_emphasis_check: bool,
-    #[serde(default)]
+    #[serde(default = "default_true")]
     pub enable_half_paren_check: bool,
-    #[serde(default)]
+    #[serde(default = "default_true")]
     pub enable_full_paren_check: bool,
+    #[serde(default)]
+    pub enable_code_span_check: bool,
+    #[serde(default)]
+    pub enable_url_check: bool,
+    #[serde(default)]
+    pub enable_heading_check: bool,
+    #[serde(default)]
+    pub enable_heading_anchor_check: bool,
+    #[serde(default)]
+    pub enable_link_target_check: bool,
+    #[serde(default)]
+    pub enable_placeholder_check: bool,
+    #[serde(default)]
+    pub enable_number_check: bool,
+    #[serde(default)]
+    pub enable_code_block_check: bool,
+    #[serde(default)]
+    pub enable_structure_check: bool,
+    #[serde(default)]
+    pub cjk_latin_space: Option<CjkSpacing>,
+    #[serde(default)]
+    pub enable_punctuation_width_check: bool,
+    #[serde(default)]
+    pub quote_style: Option<QuoteStyle>,
+    #[serde(default)]
+    pub enable_full_width_alnum_check: bool,
+    #[serde(default)]
+    pub rules: Vec<ConfigRule>,
+    #[serde(default)]
+    pub glossary: Option<PathBuf>,
+    #[serde(default)]
+    pub target_script: Option<TargetScript>,
+    #[serde(default)]
+    pub untranslated_allowlist: Vec<String>,
+    #[serde(default = "default_untranslated_threshold")]
+    pub untranslated_threshold: f64,
 }
 
 impl Default for ConfigLinter {
     fn default() -> Self {
         ConfigLinter {
-            enable_emphasis_check: false,
-            enable_half_paren_check: false,
-            enable_full_paren_check: false,
+            enable_emphasis_check: true,
+            enable_half_paren_check: true,
+            enable_full_paren_check: true,
+            enable_code_span_check: false,
+            enable_url_check: false,
+            enable_heading_check: false,
+            enable_heading_anchor_check: false,
+            enable_link_target_check: false,
+            enable_placeholder_check: false,
+            enable_number_check: false,
+            enable_code_block_check: false,
+            enable_structure_check: false,
+            cjk_latin_space: None,
+            enable_punctuation_width_check: false,
+            quote_style: None,
+            enable_full_width_alnum_check: false,
+            rules: Vec::new(),
+            glossary: None,
+            target_script: None,
+            untranslated_allowlist: Vec::new(),
+            untranslated_threshold: 0.5,
         }
     }
 }
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigSourceLinter {
+    #[serde(default)]
+    pub enable_comment_check: bool,
+    #[serde(default)]
+    pub enable_trailing_whitespace_check: bool,
+    #[serde(default)]
+    pub rules: Vec<ConfigRule>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigRule {
+    pub id: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub mode: RuleMode,
+    pub message: String,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default = "default_rule_contexts")]
+    pub contexts: Vec<LineContext>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleMode {
+    #[default]
+    MustNotMatch,
+    MustMatch,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Off,
+    Warn,
+    #[default]
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetScript {
+    Japanese,
+    Han,
+    Hangul,
+    Cyrillic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CjkSpacing {
+    Required,
+    Forbidden,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteStyle {
+    Corner,
+    Curly,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let config: Config = toml::from_str(
+            r##"
+[severity]
+modified = "warn"
+computer = "off"
+[[linter.rules]]
+id = "computer"
+pattern = "コンピュータ"
+message = "use 'コンピューター'"
+"##,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str("[severity]\nmodifed = \"off\"\n").unwrap();
+        let ret = config.validate().unwrap_err();
+        assert!(ret.to_string().starts_with("unknown rule id `modifed`"));
+    }
+}
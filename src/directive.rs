@@ -0,0 +1,171 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+static DIRECTIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^\s*<!--\s*transcheck-(disable-next-line|disable|enable)((?:[\s,]+[\w-]+)*)\s*-->\s*$",
+    )
+    .unwrap()
+});
+
+/// A range of lines in which the rules are disabled
+///
+/// If `id` is `None`, all rules except `except` are disabled.
+#[derive(Clone, Debug)]
+struct Disabled {
+    begin: usize,
+    end: usize,
+    id: Option<String>,
+    except: Vec<String>,
+}
+
+/// Suppression directives in a target file
+///
+/// * `<!-- transcheck-disable-next-line [id...] -->`
+/// * `<!-- transcheck-disable [id...] -->` / `<!-- transcheck-enable [id...] -->`
+#[derive(Clone, Debug, Default)]
+pub struct Directives {
+    disabled: Vec<Disabled>,
+}
+
+impl Directives {
+    pub fn parse(text: &str) -> Self {
+        let mut disabled = Vec::new();
+        let mut opened: Vec<Disabled> = Vec::new();
+        for (i, x) in text.lines().enumerate() {
+            let number = i + 1;
+            let cap = match DIRECTIVE.captures(x) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let ids: Vec<_> = cap[2]
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|x| !x.is_empty())
+                .map(|x| Some(String::from(x)))
+                .collect();
+            let ids = if ids.is_empty() { vec![None] } else { ids };
+
+            match &cap[1] {
+                "disable-next-line" => {
+                    for id in ids {
+                        disabled.push(Disabled {
+                            begin: number + 1,
+                            end: number + 1,
+                            id,
+                            except: Vec::new(),
+                        });
+                    }
+                }
+                "disable" => {
+                    for id in ids {
+                        opened.push(Disabled {
+                            begin: number + 1,
+                            end: usize::MAX,
+                            id,
+                            except: Vec::new(),
+                        });
+                    }
+                }
+                _ => {
+                    // `transcheck-enable` without ids enables all rules
+                    let all = ids.contains(&None);
+                    let (closed, mut rest): (Vec<_>, Vec<_>) = opened
+                        .into_iter()
+                        .partition(|x| all || x.id.is_none() || ids.contains(&x.id));
+                    for mut x in closed {
+                        // The range of all rules is continued without the enabled rules
+                        if !all && x.id.is_none() {
+                            let mut except = x.except.clone();
+                            except.extend(ids.iter().flatten().cloned());
+                            rest.push(Disabled {
+                                begin: number + 1,
+                                end: usize::MAX,
+                                id: None,
+                                except,
+                            });
+                        }
+                        x.end = number - 1;
+                        disabled.push(x);
+                    }
+                    opened = rest;
+                }
+            }
+        }
+        disabled.append(&mut opened);
+
+        Directives { disabled }
+    }
+
+    /// Check whether the line is a directive
+    pub fn is_directive(line: &str) -> bool {
+        DIRECTIVE.is_match(line)
+    }
+
+    /// Check whether the rule `id` is disabled at `line`
+    pub fn is_disabled(&self, line: usize, id: &str) -> bool {
+        self.disabled.iter().any(|x| {
+            x.begin <= line
+                && line <= x.end
+                && x.id.as_ref().map(|x| x == id).unwrap_or(true)
+                && !x.except.iter().any(|x| x == id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_directives() {
+        let text = r##"
+<!-- transcheck-disable-next-line emphasis -->
+これは**強調**です。
+<!-- transcheck-disable -->
+line 5
+<!-- transcheck-disable code-block, modified -->
+line 7
+<!-- transcheck-enable -->
+line 9
+<!-- transcheck-disable garbage -->
+line 11
+"##;
+        let ret = Directives::parse(text);
+        assert!(ret.is_disabled(3, "emphasis"));
+        assert!(!ret.is_disabled(3, "half-paren"));
+        assert!(!ret.is_disabled(4, "emphasis"));
+        assert!(ret.is_disabled(5, "emphasis"));
+        assert!(ret.is_disabled(7, "modified"));
+        assert!(!ret.is_disabled(9, "modified"));
+        assert!(ret.is_disabled(11, "garbage"));
+        assert!(!ret.is_disabled(11, "missing"));
+    }
+
+    #[test]
+    fn test_directives_enable_in_disable_all() {
+        let text = r##"
+<!-- transcheck-disable -->
+line 3
+<!-- transcheck-enable emphasis -->
+line 5
+<!-- transcheck-enable glossary -->
+line 7
+<!-- transcheck-disable emphasis -->
+line 9
+<!-- transcheck-enable -->
+line 11
+"##;
+        let ret = Directives::parse(text);
+        assert!(ret.is_disabled(3, "emphasis"));
+        assert!(!ret.is_disabled(5, "emphasis"));
+        assert!(ret.is_disabled(5, "glossary"));
+        assert!(!ret.is_disabled(7, "emphasis"));
+        assert!(!ret.is_disabled(7, "glossary"));
+        assert!(ret.is_disabled(7, "modified"));
+        assert!(ret.is_disabled(9, "emphasis"));
+        assert!(!ret.is_disabled(9, "glossary"));
+        assert!(!ret.is_disabled(11, "emphasis"));
+        assert!(!ret.is_disabled(11, "modified"));
+    }
+}
@@ -0,0 +1,174 @@
+/// Kind of a line in terms of code blocks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodeLine {
+    Text,
+    /// Opening fence with the info string
+    Open(String),
+    /// Closing fence
+    Close,
+    /// Line in a fenced code block
+    Fenced,
+    /// Line in an indented code block
+    Indented,
+}
+
+/// Code block tracker following fenced and indented code blocks of CommonMark
+///
+/// Fences in list items can be indented deeper than 3 spaces,
+/// and indented lines in list items are treated as the continuation of the item.
+#[derive(Clone, Debug, Default)]
+pub struct Fences {
+    fence: Option<(char, usize)>,
+    indented: bool,
+    list: bool,
+    paragraph: bool,
+    blank: bool,
+}
+
+impl Fences {
+    pub fn next(&mut self, line: &str) -> CodeLine {
+        let trimmed = line.trim_start();
+        let indent = indent_width(line);
+        let blank = std::mem::replace(&mut self.blank, trimmed.is_empty());
+
+        if let Some((c, len)) = self.fence {
+            let closed = parse_fence(trimmed)
+                .is_some_and(|(x, y, info)| x == c && y >= len && info.is_empty());
+            if closed && (indent <= 3 || self.list) {
+                self.fence = None;
+                return CodeLine::Close;
+            }
+            return CodeLine::Fenced;
+        }
+
+        if trimmed.is_empty() {
+            self.paragraph = false;
+            return CodeLine::Text;
+        }
+
+        if indent >= 4 && !self.list && (self.indented || !self.paragraph) {
+            self.indented = true;
+            return CodeLine::Indented;
+        }
+        self.indented = false;
+
+        if let Some((c, len, info)) = parse_fence(trimmed) {
+            if indent <= 3 || self.list {
+                self.fence = Some((c, len));
+                self.paragraph = false;
+                return CodeLine::Open(String::from(info));
+            }
+        }
+
+        if indent <= 3 && is_list_item(trimmed) {
+            self.list = true;
+        } else if indent == 0 && blank {
+            self.list = false;
+        }
+        self.paragraph = !trimmed.starts_with('#');
+        CodeLine::Text
+    }
+}
+
+/// Track code blocks of each line in `text`
+pub fn track(text: &str) -> Vec<CodeLine> {
+    let mut fences = Fences::default();
+    text.lines().map(|x| fences.next(x)).collect()
+}
+
+/// Split the indentation of an indented code block from `line`
+pub fn split_indent(line: &str) -> (&str, &str) {
+    if line.starts_with('\t') {
+        line.split_at(1)
+    } else {
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        line.split_at(usize::min(spaces, 4))
+    }
+}
+
+fn indent_width(line: &str) -> usize {
+    let mut ret = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => ret += 1,
+            '\t' => ret += 4 - ret % 4,
+            _ => break,
+        }
+    }
+    ret
+}
+
+/// Parse a fence like ```` ```rust ```` or `~~~~` into the character, the length and the info string
+fn parse_fence(trimmed: &str) -> Option<(char, usize, &str)> {
+    let c = trimmed.chars().next().filter(|x| *x == '`' || *x == '~')?;
+    let len = trimmed.chars().take_while(|x| *x == c).count();
+    let info = trimmed[len..].trim();
+    if len < 3 || (c == '`' && info.contains('`')) {
+        None
+    } else {
+        Some((c, len, info))
+    }
+}
+
+fn is_list_item(trimmed: &str) -> bool {
+    let marker = trimmed.trim_start_matches(|c: char| c.is_ascii_digit());
+    let marker = if marker.len() == trimmed.len() {
+        marker.strip_prefix(['-', '*', '+'])
+    } else {
+        marker.strip_prefix(['.', ')'])
+    };
+    marker.is_some_and(|x| x.is_empty() || x.starts_with([' ', '\t']))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_track() {
+        let text = r##"text
+````markdown
+```rust
+````
+
+~~~
+code
+~~~
+
+    indented
+    code
+text
+    not code
+
+* item
+
+      code in list
+    continuation
+"##;
+        let ret = track(text);
+        assert_eq!(
+            ret,
+            vec![
+                CodeLine::Text,
+                CodeLine::Open(String::from("markdown")),
+                CodeLine::Fenced,
+                CodeLine::Close,
+                CodeLine::Text,
+                CodeLine::Open(String::new()),
+                CodeLine::Fenced,
+                CodeLine::Close,
+                CodeLine::Text,
+                CodeLine::Indented,
+                CodeLine::Indented,
+                CodeLine::Text,
+                CodeLine::Text,
+                CodeLine::Text,
+                CodeLine::Text,
+                CodeLine::Text,
+                CodeLine::Text,
+                CodeLine::Text,
+            ]
+        );
+        assert_eq!(split_indent("      # x"), ("    ", "  # x"));
+    }
+}
@@ -1,31 +1,170 @@
+use crate::comment;
+use crate::fence::{self, CodeLine};
+use crate::journal::{Change, ChangeKind, JournalRun};
+use crate::linter::{LintError, LintErrorKind};
 use crate::matcher::{Mismatch, MismatchLines, MissingFile};
-use crate::util::{combine_line, CombinedLine};
-use anyhow::{Context, Error};
+use crate::syntax::CodeCommentSyntax;
+use crate::util::{combine_line, CombinedLine, TextFormat};
+use anyhow::{bail, Context, Error};
 use console::style;
+use std::borrow::Cow;
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 
 pub struct Fixer {
     pub dry_run: bool,
+    pub enable_code_comment_tweak: bool,
+    pub code_comment_header: String,
+    pub code_comment_syntax: CodeCommentSyntax,
 }
 
 impl Fixer {
-    pub fn fix(&self, mismatches: &[Mismatch]) -> Result<bool, Error> {
-        let mut ret = true;
+    pub fn fix(&self, mismatches: &[Mismatch]) -> Result<Vec<Change>, Error> {
+        let mut ret = Vec::new();
         for mismatch in mismatches {
             match mismatch {
                 Mismatch::MissingFile(x) => {
-                    ret = ret & self.fix_file(x)?;
+                    ret.push(self.fix_file(x)?);
                 }
                 Mismatch::MismatchLines(x) => {
-                    ret = ret & self.fix_lines(x)?;
+                    ret.append(&mut self.fix_lines(x)?);
                 }
+                // The file can't be fixed until the comment is corrected by hand
+                Mismatch::MalformedComment(_) => (),
             }
         }
         Ok(ret)
     }
 
-    fn fix_file(&self, missing: &MissingFile) -> Result<bool, Error> {
+    /// Revert the changes recorded by a fix run
+    pub fn undo(&self, run: &JournalRun) -> Result<(), Error> {
+        let mut files: Vec<(PathBuf, TextFormat, Vec<String>)> = Vec::new();
+        let mut removed_files = Vec::new();
+
+        // All changes are checked before writing anything to avoid partial undo
+        for change in run.changes.iter().rev() {
+            let path = &change.path;
+            if change.kind == ChangeKind::Copy {
+                let text = read_file(path)?;
+                if !TextFormat::detect(&text).lines(&text).eq(change.new.iter()) {
+                    bail!(
+                        "'{}' has been changed after the fix",
+                        path.to_string_lossy()
+                    );
+                }
+                self.log("Delete", &format!("{}", path.to_string_lossy()))?;
+                removed_files.push(path.clone());
+                continue;
+            }
+
+            let index = if let Some(i) = files.iter().position(|x| &x.0 == path) {
+                i
+            } else {
+                let text = read_file(path)?;
+                let format = TextFormat::detect(&text);
+                let lines = format.lines(&text).map(String::from).collect();
+                files.push((path.clone(), format, lines));
+                files.len() - 1
+            };
+            let lines = &mut files[index].2;
+
+            if change.line == 0 || change.line - 1 > lines.len() {
+                bail!(
+                    "line {} of '{}' is out of range",
+                    change.line,
+                    path.to_string_lossy()
+                );
+            }
+            let begin = change.line - 1;
+            let end = begin + change.new.len();
+            if end > lines.len() || lines[begin..end] != change.new[..] {
+                bail!(
+                    "'{}' has been changed after the fix",
+                    path.to_string_lossy()
+                );
+            }
+
+            self.log(
+                "Revert",
+                &format!("{}:{}", path.to_string_lossy(), change.line),
+            )?;
+            lines.splice(begin..end, change.old.iter().cloned());
+        }
+
+        if !self.dry_run {
+            for (path, format, lines) in &files {
+                write_file(path, &format.join(lines))?;
+            }
+            for path in &removed_files {
+                fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove '{}'", path.to_string_lossy()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fix lint errors which can be corrected automatically
+    pub fn fix_lint(&self, errors: &[LintError]) -> Result<Vec<Change>, Error> {
+        let mut ret = Vec::new();
+
+        let mut paths: Vec<&Path> = Vec::new();
+        for error in errors.iter().filter(|x| is_fixable(&x.kind)) {
+            if !paths.contains(&error.path.as_path()) {
+                paths.push(&error.path);
+            }
+        }
+
+        for path in paths {
+            let text = read_file(path)?;
+            let format = TextFormat::detect(&text);
+            let mut lines: Vec<_> = format.lines(&text).map(String::from).collect();
+
+            let mut changed = false;
+            for (i, line) in lines.iter_mut().enumerate() {
+                let mut errors: Vec<_> = errors
+                    .iter()
+                    .filter(|x| x.path == path && x.line.number == i + 1 && is_fixable(&x.kind))
+                    .collect();
+
+                // Skip the line if it differs from the linted one ( e.g. tweaked code comment )
+                if errors.is_empty() || errors[0].line.content != *line {
+                    continue;
+                }
+
+                // Fix from the end of the line to keep the byte ranges of the preceding errors
+                errors.sort_by_key(|x| std::cmp::Reverse(x.start));
+                let mut fixed = line.clone();
+                let mut last_start = fixed.len();
+                for error in errors {
+                    if error.end > last_start {
+                        continue;
+                    }
+                    fix_lint_error(&mut fixed, error);
+                    last_start = error.start;
+                }
+
+                self.log("   Fix", &format!("{}:{}", path.to_string_lossy(), i + 1))?;
+                ret.push(Change {
+                    kind: ChangeKind::Modify,
+                    path: PathBuf::from(path),
+                    line: i + 1,
+                    old: vec![line.clone()],
+                    new: vec![fixed.clone()],
+                });
+                *line = fixed;
+                changed = true;
+            }
+
+            if changed && !self.dry_run {
+                write_file(path, &format.join(&lines))?;
+            }
+        }
+
+        Ok(ret)
+    }
+
+    fn fix_file(&self, missing: &MissingFile) -> Result<Change, Error> {
         println!(
             "{}{}",
             style("  Copy").green().bold(),
@@ -40,7 +179,19 @@ impl Fixer {
         if !self.dry_run {
             fs::copy(&missing.source_path, &missing.target_path)?;
         }
-        Ok(true)
+
+        // The copied lines are recorded to check that the file is not changed before undo
+        let text = read_file(&missing.source_path)?;
+        Ok(Change {
+            kind: ChangeKind::Copy,
+            path: missing.target_path.clone(),
+            line: 0,
+            old: Vec::new(),
+            new: TextFormat::detect(&text)
+                .lines(&text)
+                .map(String::from)
+                .collect(),
+        })
     }
 
     fn log(&self, header: &str, message: &str) -> Result<(), Error> {
@@ -52,118 +203,536 @@ impl Fixer {
         Ok(())
     }
 
-    fn fix_lines(&self, mismatch: &MismatchLines) -> Result<bool, Error> {
-        if !mismatch.lines.is_empty() {
-            let mut lines = combine_line(mismatch);
+    fn fix_lines(&self, mismatch: &MismatchLines) -> Result<Vec<Change>, Error> {
+        if mismatch.lines.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let target_path = &mismatch.target_path;
+        let target = read_file(target_path)?;
+
+        let (modified, changes) = self.fix_content(mismatch, &target)?;
+
+        if !self.dry_run {
+            write_file(target_path, &modified)?;
+        }
+        Ok(changes)
+    }
+
+    fn fix_content(
+        &self,
+        mismatch: &MismatchLines,
+        target: &str,
+    ) -> Result<(String, Vec<Change>), Error> {
+        let mut lines = combine_line(mismatch);
+        let target_path = &mismatch.target_path;
 
-            let mut target = String::new();
-            let target_path = &mismatch.target_path;
+        // BOM, line ending and trailing new line of the target are kept as is
+        let format = TextFormat::detect(target);
 
-            {
-                let mut target_reader =
-                    BufReader::new(File::open(target_path).with_context(|| {
-                        format!("Failed to open '{}'", target_path.to_string_lossy())
-                    })?);
-                target_reader.read_to_string(&mut target).with_context(|| {
-                    format!("Failed to read '{}'", target_path.to_string_lossy())
-                })?;
+        // sort by line number
+        lines.sort_by_key(|x| match x {
+            CombinedLine::Modified(x) => x.1.number,
+            CombinedLine::Missing(x) => x[0].last_both,
+            CombinedLine::Garbage(x) => x[0].number,
+        });
+
+        let mut modified_lines = Vec::new();
+        let mut missing_lines = Vec::new();
+        let mut garbage_lines = Vec::new();
+        for line in lines {
+            match line {
+                CombinedLine::Modified(x) => modified_lines.push(x),
+                CombinedLine::Missing(x) => missing_lines.push(x),
+                CombinedLine::Garbage(x) => garbage_lines.push(x),
             }
+        }
 
-            // sort by line number
-            lines.sort_by_key(|x| match x {
-                CombinedLine::Modified(x) => x.1.number,
-                CombinedLine::Missing(x) => x[0].last_both,
-                CombinedLine::Garbage(x) => x[0].number,
-            });
+        let target_lines: Vec<_> = format.lines(target).collect();
+        let comments = comment::track(&target_lines.join("\n"));
+        let codes = fence::track(&target_lines.join("\n"));
+        let syntaxes = self.code_comment_syntax.lines(&codes);
 
-            let mut modified_lines = Vec::new();
-            let mut missing_lines = Vec::new();
-            let mut garbage_lines = Vec::new();
-            for line in lines {
-                match line {
-                    CombinedLine::Modified(x) => modified_lines.push(x),
-                    CombinedLine::Missing(x) => missing_lines.push(x),
-                    CombinedLine::Garbage(x) => garbage_lines.push(x),
+        // Garbage sharing the line with translation or comment delimiters is stripped from the line
+        let mut stripped_lines = Vec::new();
+        let mut garbage_runs = Vec::new();
+        for x in garbage_lines {
+            let mut run = Vec::new();
+            for line in x {
+                let stripped = target_lines
+                    .get(line.number - 1)
+                    .and_then(|x| strip_original(x, &line.content));
+                if let Some(stripped) = stripped {
+                    stripped_lines.push((line.number, stripped));
+                    if !run.is_empty() {
+                        garbage_runs.push(std::mem::take(&mut run));
+                    }
+                } else {
+                    run.push(line);
                 }
             }
+            if !run.is_empty() {
+                garbage_runs.push(run);
+            }
+        }
 
-            let mut modified_iter = modified_lines.iter().peekable();
-            let mut missing_iter = missing_lines.iter().peekable();
-            let mut grabage_iter = garbage_lines.iter().peekable();
+        let mut modified_iter = modified_lines.iter().peekable();
+        let mut missing_iter = missing_lines.iter().peekable();
+        let mut grabage_iter = garbage_runs.iter().peekable();
 
-            let mut modified = String::new();
-            let mut removed_numbers = Vec::new();
+        let mut modified = Vec::new();
+        let mut changes = Vec::new();
+        let mut removed_numbers = Vec::new();
 
-            for (i, line) in target.lines().enumerate() {
-                let number = i + 1;
-                if removed_numbers.contains(&number) {
-                    continue;
-                }
+        for (i, line) in target_lines.iter().copied().enumerate() {
+            let number = i + 1;
+            let code = codes.get(i).cloned().unwrap_or(CodeLine::Text);
 
-                let mut line_pushed = false;
+            if removed_numbers.contains(&number) {
+                continue;
+            }
 
-                if let Some(x) = modified_iter.peek() {
-                    if x.1.number == number {
-                        self.log(
-                            "Modify",
-                            &format!("{}:{}", target_path.to_string_lossy(), number),
-                        )?;
-                        modified.push_str(&format!("{}\n", x.0.content));
-                        line_pushed = true;
-                        modified_iter.next();
-                    }
-                }
+            let mut line_pushed = false;
+
+            if let Some((_, x)) = stripped_lines.iter().find(|x| x.0 == number) {
+                self.log(
+                    "Modify",
+                    &format!("{}:{}", target_path.to_string_lossy(), number),
+                )?;
+                modified.push(Cow::Borrowed(x.as_str()));
+                line_pushed = true;
+                changes.push(Change {
+                    kind: ChangeKind::Modify,
+                    path: target_path.clone(),
+                    line: modified.len(),
+                    old: vec![String::from(line)],
+                    new: vec![x.clone()],
+                });
+            }
 
-                if let Some(x) = missing_iter.peek() {
-                    if x[0].last_both == number {
-                        self.log(
-                            "Insert",
-                            &format!("{}:{}", target_path.to_string_lossy(), number),
-                        )?;
-                        if !line_pushed {
-                            modified.push_str(&format!("{}\n", line));
-                            line_pushed = true;
-                        }
-                        for line in *x {
-                            modified.push_str(&format!("{}\n", line.content));
-                        }
-                        missing_iter.next();
+            if let Some(x) = modified_iter.peek() {
+                if x.1.number == number {
+                    self.log(
+                        "Modify",
+                        &format!("{}:{}", target_path.to_string_lossy(), number),
+                    )?;
+                    // The header stripped by the matcher should be restored
+                    let (indent, code_line) = match code {
+                        CodeLine::Indented => fence::split_indent(line),
+                        _ => ("", line),
+                    };
+                    let header = syntaxes
+                        .get(i)
+                        .copied()
+                        .flatten()
+                        .map(|x| x.header_or(&self.code_comment_header))
+                        .filter(|x| self.enable_code_comment_tweak && code_line.starts_with(x));
+                    if let Some(header) = header {
+                        let content = x.0.content.strip_prefix(indent).unwrap_or(&x.0.content);
+                        modified.push(Cow::Owned(format!("{}{}{}", indent, header, content)));
+                    } else if !x.1.content.is_empty()
+                        && line != x.1.content
+                        && line.contains(&x.1.content)
+                    {
+                        // Comment delimiters and translation in the same line are kept
+                        modified.push(Cow::Owned(line.replacen(&x.1.content, &x.0.content, 1)));
+                    } else {
+                        modified.push(Cow::Borrowed(x.0.content.as_str()));
                     }
+                    line_pushed = true;
+                    changes.push(Change {
+                        kind: ChangeKind::Modify,
+                        path: target_path.clone(),
+                        line: modified.len(),
+                        old: vec![String::from(line)],
+                        new: vec![modified[modified.len() - 1].to_string()],
+                    });
+                    modified_iter.next();
                 }
+            }
 
-                if let Some(x) = grabage_iter.peek() {
-                    if x[0].number == number {
-                        self.log(
-                            "Remove",
-                            &format!("{}:{}", target_path.to_string_lossy(), number),
-                        )?;
-                        // line remove
+            if let Some(x) = missing_iter.peek() {
+                if x[0].last_both == number {
+                    self.log(
+                        "Insert",
+                        &format!("{}:{}", target_path.to_string_lossy(), number),
+                    )?;
+                    if !line_pushed {
+                        modified.push(Cow::Borrowed(line));
                         line_pushed = true;
-                        for line in &x[1..] {
-                            removed_numbers.push(line.number);
-                        }
-                        grabage_iter.next();
                     }
+                    // Lines following a comment closed in the same line are commented out one by one
+                    let closed = comments.get(i).and_then(|x| x.original.as_ref());
+                    let closed = closed.is_some_and(|x| line[x.end..].contains("-->"));
+                    let new: Vec<_> = x
+                        .iter()
+                        .map(|x| {
+                            if closed && !x.content.is_empty() {
+                                format!("<!-- {} -->", x.content)
+                            } else {
+                                x.content.clone()
+                            }
+                        })
+                        .collect();
+                    changes.push(Change {
+                        kind: ChangeKind::Insert,
+                        path: target_path.clone(),
+                        line: modified.len() + 1,
+                        old: Vec::new(),
+                        new: new.clone(),
+                    });
+                    modified.extend(new.into_iter().map(Cow::Owned));
+                    missing_iter.next();
                 }
+            }
 
-                if !line_pushed {
-                    modified.push_str(&format!("{}\n", line));
+            if let Some(x) = grabage_iter.peek() {
+                if x[0].number == number {
+                    self.log(
+                        "Remove",
+                        &format!("{}:{}", target_path.to_string_lossy(), number),
+                    )?;
+                    // line remove
+                    let begin = if line_pushed { i + 1 } else { i };
+                    let end = usize::min(i + x.len(), target_lines.len());
+                    line_pushed = true;
+                    changes.push(Change {
+                        kind: ChangeKind::Remove,
+                        path: target_path.clone(),
+                        line: modified.len() + 1,
+                        old: target_lines[begin..end]
+                            .iter()
+                            .map(|x| String::from(*x))
+                            .collect(),
+                        new: Vec::new(),
+                    });
+                    for line in &x[1..] {
+                        removed_numbers.push(line.number);
+                    }
+                    grabage_iter.next();
                 }
             }
 
-            if !self.dry_run {
-                let mut target_writer =
-                    BufWriter::new(File::create(target_path).with_context(|| {
-                        format!("Failed to open '{}'", target_path.to_string_lossy())
-                    })?);
-                target_writer
-                    .write_all(modified.as_bytes())
-                    .with_context(|| {
-                        format!("Failed to write '{}'", target_path.to_string_lossy())
-                    })?;
-                target_writer.flush()?;
+            if !line_pushed {
+                modified.push(Cow::Borrowed(line));
             }
         }
-        Ok(true)
+
+        Ok((format.join(&modified), changes))
+    }
+}
+
+fn is_fixable(kind: &LintErrorKind) -> bool {
+    matches!(
+        kind,
+        LintErrorKind::EmphasisWithoutSpace
+            | LintErrorKind::HalfParenWithNonAscii
+            | LintErrorKind::FullParenWithoutNonAscii
+    )
+}
+
+fn fix_lint_error(line: &mut String, error: &LintError) {
+    let (start, end) = (error.start, error.end);
+    match error.kind {
+        LintErrorKind::EmphasisWithoutSpace => {
+            if !line[end..].is_empty() && !line[end..].starts_with(' ') {
+                line.insert(end, ' ');
+            }
+            if !line[..start].is_empty() && !line[..start].ends_with(' ') {
+                line.insert(start, ' ');
+            }
+        }
+        LintErrorKind::HalfParenWithNonAscii => {
+            let inner = &line[start + '('.len_utf8()..end - ')'.len_utf8()];
+            let fixed = format!("（{}）", inner);
+            line.replace_range(start..end, &fixed);
+        }
+        LintErrorKind::FullParenWithoutNonAscii => {
+            let inner = &line[start + '（'.len_utf8()..end - '）'.len_utf8()];
+            let fixed = format!("({})", inner);
+            line.replace_range(start..end, &fixed);
+        }
+        _ => (),
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, Error> {
+    let mut ret = String::new();
+    let mut reader = BufReader::new(
+        File::open(path).with_context(|| format!("Failed to open '{}'", path.to_string_lossy()))?,
+    );
+    reader
+        .read_to_string(&mut ret)
+        .with_context(|| format!("Failed to read '{}'", path.to_string_lossy()))?;
+    Ok(ret)
+}
+
+fn write_file(path: &Path, text: &str) -> Result<(), Error> {
+    let mut writer = BufWriter::new(
+        File::create(path)
+            .with_context(|| format!("Failed to open '{}'", path.to_string_lossy()))?,
+    );
+    writer
+        .write_all(text.as_bytes())
+        .with_context(|| format!("Failed to write '{}'", path.to_string_lossy()))?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Remove the commented original text `original` from `line` keeping the rest
+///
+/// Returns `None` if `line` consists of `original` only.
+fn strip_original(line: &str, original: &str) -> Option<String> {
+    if original.is_empty() || line == original {
+        return None;
+    }
+    let pos = line.find(original)?;
+    let begin = line[..pos].rfind("<!--");
+    let end = line[pos + original.len()..]
+        .find("-->")
+        .map(|x| pos + original.len() + x + 3);
+    let ret = match (begin, end) {
+        (Some(begin), Some(end)) => {
+            let (left, right) = (line[..begin].trim_end(), line[end..].trim_start());
+            if left.is_empty() || right.is_empty() {
+                format!("{}{}", left, right)
+            } else {
+                format!("{} {}", left, right)
+            }
+        }
+        _ => format!("{}{}", &line[..pos], &line[pos + original.len()..])
+            .trim_end()
+            .to_string(),
+    };
+    Some(ret)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::linter::Linter;
+    use crate::matcher::{
+        GarbageLine, Line, LineContext, MismatchLine, MissingLine, ModifiedLine, TargetOnly,
+    };
+    use crate::util::TempDir;
+    use std::path::PathBuf;
+
+    fn line(number: usize, content: &str) -> Line {
+        Line {
+            number,
+            content: String::from(content),
+            last_both: 0,
+            html_comment: false,
+            code_not_comment: false,
+            context: LineContext::Text,
+        }
+    }
+
+    fn fixer(enable_code_comment_tweak: bool) -> Fixer {
+        Fixer {
+            dry_run: true,
+            enable_code_comment_tweak,
+            code_comment_header: String::from("# // "),
+            code_comment_syntax: CodeCommentSyntax::default(),
+        }
+    }
+
+    fn modified(source: &str, target: &str) -> MismatchLines {
+        MismatchLines {
+            source_path: PathBuf::from("source.md"),
+            target_path: PathBuf::from("target.md"),
+            lines: vec![MismatchLine::Modified(ModifiedLine {
+                source: line(1, source),
+                target: line(2, target),
+            })],
+        }
+    }
+
+    #[test]
+    fn test_fix_content_keep_crlf() {
+        let fixer = fixer(false);
+        let mismatch = modified("Orange", "Apple");
+        let ret = fixer
+            .fix_content(&mismatch, "<!--\r\nApple\r\n-->\r\nりんご\r\n")
+            .unwrap()
+            .0;
+        assert_eq!(ret, "<!--\r\nOrange\r\n-->\r\nりんご\r\n");
+    }
+
+    #[test]
+    fn test_fix_content_keep_bom() {
+        let fixer = fixer(false);
+        let mismatch = modified("Orange", "Apple");
+        let ret = fixer
+            .fix_content(&mismatch, "\u{feff}<!--\nApple\n-->\nりんご\n")
+            .unwrap()
+            .0;
+        assert_eq!(ret, "\u{feff}<!--\nOrange\n-->\nりんご\n");
+    }
+
+    #[test]
+    fn test_fix_content_keep_no_trailing_new_line() {
+        let fixer = fixer(false);
+        let mismatch = modified("Orange", "Apple");
+        let ret = fixer
+            .fix_content(&mismatch, "<!--\nApple\n-->\nりんご")
+            .unwrap()
+            .0;
+        assert_eq!(ret, "<!--\nOrange\n-->\nりんご");
+    }
+
+    #[test]
+    fn test_fix_content_inline_comment() {
+        let fixer = fixer(false);
+        let mismatch = modified("Orange", "Apple");
+        let ret = fixer
+            .fix_content(&mismatch, "りんご <!-- Lemon -->\n<!-- Apple -->\n")
+            .unwrap()
+            .0;
+        assert_eq!(ret, "りんご <!-- Lemon -->\n<!-- Orange -->\n");
+    }
+
+    #[test]
+    fn test_fix_content_inline_garbage() {
+        let fixer = fixer(false);
+        let mismatch = MismatchLines {
+            source_path: PathBuf::from("source.md"),
+            target_path: PathBuf::from("target.md"),
+            lines: vec![
+                MismatchLine::Garbage(GarbageLine {
+                    target: line(1, "Apple"),
+                }),
+                MismatchLine::Garbage(GarbageLine {
+                    target: line(2, "Orange"),
+                }),
+                MismatchLine::Garbage(GarbageLine {
+                    target: line(4, "Peach"),
+                }),
+            ],
+        };
+        let ret = fixer
+            .fix_content(
+                &mismatch,
+                "<!-- Apple\nOrange\n-->\nりんご <!-- Peach -->\n",
+            )
+            .unwrap()
+            .0;
+        assert_eq!(ret, "<!--\n-->\nりんご\n");
+    }
+
+    #[test]
+    fn test_fix_content_inline_missing() {
+        let fixer = fixer(false);
+        let mut missing = line(2, "Orange");
+        missing.last_both = 1;
+        let mismatch = MismatchLines {
+            source_path: PathBuf::from("source.md"),
+            target_path: PathBuf::from("target.md"),
+            lines: vec![MismatchLine::Missing(MissingLine { source: missing })],
+        };
+        let ret = fixer
+            .fix_content(&mismatch, "<!-- Apple -->\nりんご\n")
+            .unwrap()
+            .0;
+        assert_eq!(ret, "<!-- Apple -->\n<!-- Orange -->\nりんご\n");
+    }
+
+    #[test]
+    fn test_fix_content_code_comment_header() {
+        let fixer = fixer(true);
+        let mismatch = modified("let a = c; // comment", "let a = b; // comment");
+        let ret = fixer
+            .fix_content(
+                &mismatch,
+                "```rust\n# // let a = b; // comment\nlet a = b; // コメント\n```\n",
+            )
+            .unwrap()
+            .0;
+        assert_eq!(
+            ret,
+            "```rust\n# // let a = c; // comment\nlet a = b; // コメント\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut fixer = fixer(false);
+        fixer.dry_run = false;
+        let dir = TempDir::new();
+        let text = "<!--\r\nApple\r\n-->\r\nりんご";
+        let path = dir.write("undo.md", text);
+
+        let mut mismatch = modified("Orange", "Apple");
+        mismatch.target_path = path.clone();
+        let changes = fixer.fix_lines(&mismatch).unwrap();
+        assert_eq!(read_file(&path).unwrap(), "<!--\r\nOrange\r\n-->\r\nりんご");
+
+        fixer.undo(&JournalRun { changes }).unwrap();
+        assert_eq!(read_file(&path).unwrap(), text);
+    }
+
+    #[test]
+    fn test_undo_out_of_range() {
+        let fixer = fixer(false);
+        let dir = TempDir::new();
+        let path = dir.write("undo.md", "りんご\n");
+
+        for line in [0, 3] {
+            let change = Change {
+                kind: ChangeKind::Remove,
+                path: path.clone(),
+                line,
+                old: vec![String::from("Apple")],
+                new: Vec::new(),
+            };
+            let ret = fixer.undo(&JournalRun {
+                changes: vec![change],
+            });
+            assert!(ret.is_err());
+        }
+        assert_eq!(read_file(&path).unwrap(), "りんご\n");
+    }
+
+    #[test]
+    fn test_fix_lint() {
+        let mut fixer = fixer(false);
+        fixer.dry_run = false;
+        let dir = TempDir::new();
+        let path = dir.write(
+            "fix-lint.md",
+            "\u{feff}これは**「強調」**です(注意)。\r\n（note）\r\n",
+        );
+
+        let linter = Linter {
+            enable_emphasis_check: true,
+            enable_half_paren_check: true,
+            enable_full_paren_check: true,
+            ..Default::default()
+        };
+        let target_only = TargetOnly {
+            source_path: PathBuf::from("source.md"),
+            target_path: path.clone(),
+            lines: vec![
+                line(1, "これは**「強調」**です(注意)。"),
+                line(2, "（note）"),
+            ],
+            pairs: Vec::new(),
+            directives: Default::default(),
+        };
+        let errors = linter.check(vec![target_only]).unwrap();
+        assert_eq!(errors.len(), 3);
+
+        let changes = fixer.fix_lint(&errors).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            read_file(&path).unwrap(),
+            "\u{feff}これは **「強調」** です（注意）。\r\n(note)\r\n"
+        );
+
+        fixer.undo(&JournalRun { changes }).unwrap();
+        assert_eq!(
+            read_file(&path).unwrap(),
+            "\u{feff}これは**「強調」**です(注意)。\r\n（note）\r\n"
+        );
     }
 }
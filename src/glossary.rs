@@ -0,0 +1,164 @@
+use anyhow::{bail, Context, Error};
+use regex::Regex;
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+pub struct GlossaryTerm {
+    pub source: String,
+    pub translations: Vec<String>,
+    pub regex: Regex,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Glossary {
+    pub terms: Vec<GlossaryTerm>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Translations {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Glossary {
+    /// Load glossary from TOML or CSV file
+    ///
+    /// * TOML: `"source term" = "translation"` or `"source term" = ["translation", ...]`
+    /// * CSV : `source term,translation[,translation...]`
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let mut reader = BufReader::new(
+            File::open(path)
+                .with_context(|| format!("Failed to open '{}'", path.to_string_lossy()))?,
+        );
+        let mut s = String::new();
+        reader
+            .read_to_string(&mut s)
+            .with_context(|| format!("Failed to read '{}'", path.to_string_lossy()))?;
+
+        let ret = if path.extension().map(|x| x == "csv").unwrap_or(false) {
+            Glossary::from_csv(&s)
+        } else {
+            Glossary::from_toml(&s)
+        };
+        ret.with_context(|| format!("Failed to parse glossary '{}'", path.to_string_lossy()))
+    }
+
+    pub fn from_toml(s: &str) -> Result<Self, Error> {
+        let terms: BTreeMap<String, Translations> = toml::from_str(s)?;
+
+        let mut ret = Glossary::default();
+        for (source, translations) in terms {
+            let translations = match translations {
+                Translations::One(x) => vec![x],
+                Translations::Many(x) => x,
+            };
+            ret.add(source, translations)?;
+        }
+        Ok(ret)
+    }
+
+    pub fn from_csv(s: &str) -> Result<Self, Error> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .comment(Some(b'#'))
+            .trim(csv::Trim::All)
+            .from_reader(s.as_bytes());
+
+        let mut ret = Glossary::default();
+        for record in reader.records() {
+            let record = record?;
+            let mut fields = record.iter().filter(|x| !x.is_empty());
+            let source = fields.next();
+            let translations: Vec<_> = fields.map(String::from).collect();
+            match source {
+                Some(source) if !translations.is_empty() => {
+                    ret.add(String::from(source), translations)?
+                }
+                Some(source) => bail!("Translation of '{}' is not found", source),
+                None => (),
+            }
+        }
+        Ok(ret)
+    }
+
+    fn add(&mut self, source: String, translations: Vec<String>) -> Result<(), Error> {
+        // Word boundary is required only at the side of word characters to match "C++" or ".NET"
+        let boundary = |c: Option<char>| {
+            if c.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                r"\b"
+            } else {
+                ""
+            }
+        };
+        let regex = Regex::new(&format!(
+            r"(?i){}{}{}",
+            boundary(source.chars().next()),
+            regex::escape(&source),
+            boundary(source.chars().last()),
+        ))?;
+        self.terms.push(GlossaryTerm {
+            source,
+            translations,
+            regex,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let s = r##"
+"borrow checker" = "借用チェッカー"
+"crate" = ["クレート", "crate"]
+        "##;
+        let ret = Glossary::from_toml(s).unwrap();
+        assert_eq!(ret.terms.len(), 2);
+        assert_eq!(ret.terms[0].source, "borrow checker");
+        assert_eq!(ret.terms[0].translations, vec!["借用チェッカー"]);
+        assert_eq!(ret.terms[1].translations, vec!["クレート", "crate"]);
+        assert!(ret.terms[0].regex.is_match("The Borrow Checker"));
+        assert!(!ret.terms[1].regex.is_match("crates.io is not a term"));
+    }
+
+    #[test]
+    fn test_non_word_boundary() {
+        let s = r##"
+"C++" = "C++"
+".NET" = ".NET"
+"Node.js" = "Node.js"
+        "##;
+        let ret = Glossary::from_toml(s).unwrap();
+        let term = |x| &ret.terms.iter().find(|y| y.source == x).unwrap().regex;
+        assert!(term("C++").is_match("C++ and Rust"));
+        assert!(term("C++").is_match("(C++)"));
+        assert!(!term("C++").is_match("ObjC++"));
+        assert!(term(".NET").is_match("Use .NET here"));
+        assert!(!term(".NET").is_match("Use .NETwork"));
+        assert!(term("Node.js").is_match("Node.js."));
+        assert!(!term("Node.js").is_match("Node.json"));
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let s = r##"
+# source,translation
+borrow checker, 借用チェッカー
+"trait, object",トレイトオブジェクト
+        "##;
+        let ret = Glossary::from_csv(s).unwrap();
+        assert_eq!(ret.terms.len(), 2);
+        assert_eq!(ret.terms[0].translations, vec!["借用チェッカー"]);
+        assert_eq!(ret.terms[1].source, "trait, object");
+        assert!(Glossary::from_csv("lifetime\n").is_err());
+    }
+}
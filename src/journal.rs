@@ -0,0 +1,115 @@
+use anyhow::{Context, Error};
+use serde_derive::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Journal {
+    #[serde(default)]
+    pub runs: Vec<JournalRun>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct JournalRun {
+    #[serde(default)]
+    pub changes: Vec<Change>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Copy,
+    Modify,
+    Insert,
+    Remove,
+}
+
+/// A change applied to a target file
+///
+/// `line` is the first line of `new` in the fixed file,
+/// so the change can be reverted by replacing `new` with `old` at `line`.
+/// `new` of `Copy` is the copied lines, and `path` is absolute to undo from any directory.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub path: PathBuf,
+    #[serde(default)]
+    pub line: usize,
+    #[serde(default)]
+    pub old: Vec<String>,
+    #[serde(default)]
+    pub new: Vec<String>,
+}
+
+impl Journal {
+    pub fn load(path: &Path) -> Result<Journal, Error> {
+        if !path.exists() {
+            return Ok(Journal::default());
+        }
+
+        let mut reader = BufReader::new(
+            File::open(path)
+                .with_context(|| format!("Failed to open '{}'", path.to_string_lossy()))?,
+        );
+        let mut s = String::new();
+        reader
+            .read_to_string(&mut s)
+            .with_context(|| format!("Failed to read '{}'", path.to_string_lossy()))?;
+        let ret = toml::from_str(&s)
+            .with_context(|| format!("Failed to parse toml '{}'", path.to_string_lossy()))?;
+        Ok(ret)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if self.runs.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove '{}'", path.to_string_lossy()))?;
+            }
+            return Ok(());
+        }
+
+        let s = toml::to_string(self)
+            .with_context(|| format!("Failed to serialize '{}'", path.to_string_lossy()))?;
+        let mut writer = BufWriter::new(
+            File::create(path)
+                .with_context(|| format!("Failed to open '{}'", path.to_string_lossy()))?,
+        );
+        writer
+            .write_all(s.as_bytes())
+            .with_context(|| format!("Failed to write '{}'", path.to_string_lossy()))?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_journal_roundtrip() {
+        let journal = Journal {
+            runs: vec![JournalRun {
+                changes: vec![Change {
+                    kind: ChangeKind::Modify,
+                    path: PathBuf::from("src/ch01.md"),
+                    line: 2,
+                    old: vec![String::from("Apple \"quoted\"")],
+                    new: vec![String::from("Orange")],
+                }],
+            }],
+        };
+        let s = toml::to_string(&journal).unwrap();
+        let ret: Journal = toml::from_str(&s).unwrap();
+        assert_eq!(ret.runs.len(), 1);
+        assert_eq!(ret.runs[0].changes[0].kind, ChangeKind::Modify);
+        assert_eq!(ret.runs[0].changes[0].line, 2);
+        assert_eq!(ret.runs[0].changes[0].old, vec!["Apple \"quoted\""]);
+        assert_eq!(ret.runs[0].changes[0].new, vec!["Orange"]);
+    }
+}
@@ -1,7 +1,18 @@
-use crate::matcher::{Line, TargetOnly};
-use anyhow::Error;
+use crate::comment::{self, CommentErrorKind};
+use crate::config::{CjkSpacing, ConfigRule, QuoteStyle, RuleMode, Severity, TargetScript};
+use crate::fence::{CodeLine, Fences};
+use crate::glossary::Glossary;
+use crate::matcher::{Line, LineContext, Matcher, TargetOnly, TranslationPair};
+use crate::syntax::{CodeCommentSyntax, CommentScanner};
+use anyhow::{Context, Error};
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
 use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 
 #[derive(Clone, Debug)]
 pub struct LintError {
@@ -10,6 +21,7 @@ pub struct LintError {
     pub line: Line,
     pub start: usize,
     pub end: usize,
+    pub severity: Severity,
 }
 
 #[derive(Clone, Debug)]
@@ -18,66 +30,718 @@ pub enum LintErrorKind {
     EmphasisMismatch,
     HalfParenWithNonAscii,
     FullParenWithoutNonAscii,
+    Custom {
+        id: String,
+        message: String,
+    },
+    GlossaryMismatch {
+        source: String,
+        translations: Vec<String>,
+    },
+    CodeSpanNotPreserved {
+        text: String,
+    },
+    UrlNotPreserved {
+        text: String,
+    },
+    HeadingLevelMismatch {
+        original: Option<usize>,
+        translated: Option<usize>,
+    },
+    HeadingAnchorNotPreserved {
+        text: String,
+    },
+    LinkTargetNotFound {
+        text: String,
+    },
+    LinkAnchorNotFound {
+        text: String,
+    },
+    PlaceholderNotPreserved {
+        text: String,
+    },
+    NumberNotPreserved {
+        text: String,
+    },
+    CodeBlockInfoMismatch {
+        original: String,
+        translated: String,
+    },
+    CodeBlockMismatch {
+        original: String,
+    },
+    CodeBlockMissing {
+        original: String,
+    },
+    CodeBlockGarbage,
+    ListMismatch {
+        original: Vec<usize>,
+        translated: Vec<usize>,
+    },
+    TableMismatch {
+        original: Vec<String>,
+        translated: Vec<String>,
+    },
+    BlockquoteMismatch {
+        original: usize,
+        translated: usize,
+    },
+    FootnoteNotPreserved {
+        text: String,
+    },
+    Untranslated,
+    CjkSpaceRequired,
+    CjkSpaceForbidden,
+    HalfPunctuationWithCjk,
+    QuoteStyleMismatch {
+        style: QuoteStyle,
+    },
+    FullWidthAlnum,
+    InlineComment,
+    UnterminatedComment,
+    MisorderedComment,
+    TrailingWhitespace,
+}
+
+impl LintErrorKind {
+    /// Rule id used by suppression directives
+    pub fn id(&self) -> &str {
+        match self {
+            LintErrorKind::EmphasisWithoutSpace => "emphasis",
+            LintErrorKind::EmphasisMismatch => "emphasis",
+            LintErrorKind::HalfParenWithNonAscii => "half-paren",
+            LintErrorKind::FullParenWithoutNonAscii => "full-paren",
+            LintErrorKind::Custom { id, .. } => id,
+            LintErrorKind::GlossaryMismatch { .. } => "glossary",
+            LintErrorKind::CodeSpanNotPreserved { .. } => "code-span",
+            LintErrorKind::UrlNotPreserved { .. } => "url",
+            LintErrorKind::HeadingLevelMismatch { .. } => "heading",
+            LintErrorKind::HeadingAnchorNotPreserved { .. } => "heading-anchor",
+            LintErrorKind::LinkTargetNotFound { .. } => "link-target",
+            LintErrorKind::LinkAnchorNotFound { .. } => "link-target",
+            LintErrorKind::PlaceholderNotPreserved { .. } => "placeholder",
+            LintErrorKind::NumberNotPreserved { .. } => "number",
+            LintErrorKind::CodeBlockInfoMismatch { .. } => "code-block",
+            LintErrorKind::CodeBlockMismatch { .. } => "code-block",
+            LintErrorKind::CodeBlockMissing { .. } => "code-block",
+            LintErrorKind::CodeBlockGarbage => "code-block",
+            LintErrorKind::ListMismatch { .. } => "structure",
+            LintErrorKind::TableMismatch { .. } => "structure",
+            LintErrorKind::BlockquoteMismatch { .. } => "structure",
+            LintErrorKind::FootnoteNotPreserved { .. } => "structure",
+            LintErrorKind::Untranslated => "untranslated",
+            LintErrorKind::CjkSpaceRequired => "cjk-space",
+            LintErrorKind::CjkSpaceForbidden => "cjk-space",
+            LintErrorKind::HalfPunctuationWithCjk => "punctuation-width",
+            LintErrorKind::QuoteStyleMismatch { .. } => "quote-style",
+            LintErrorKind::FullWidthAlnum => "full-width-alnum",
+            LintErrorKind::InlineComment => "comment",
+            LintErrorKind::UnterminatedComment => "comment",
+            LintErrorKind::MisorderedComment => "comment",
+            LintErrorKind::TrailingWhitespace => "trailing-whitespace",
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
+pub struct Rule {
+    pub id: String,
+    pub regex: Regex,
+    pub mode: RuleMode,
+    pub message: String,
+    pub severity: Severity,
+    pub contexts: Vec<LineContext>,
+}
+
+impl Rule {
+    pub fn new(config: &ConfigRule) -> Result<Self, Error> {
+        let regex = Regex::new(&config.pattern)
+            .with_context(|| format!("Failed to parse pattern of rule '{}'", config.id))?;
+        Ok(Rule {
+            id: config.id.clone(),
+            regex,
+            mode: config.mode,
+            message: config.message.clone(),
+            severity: config.severity,
+            contexts: config.contexts.clone(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 pub struct Linter {
     pub enable_emphasis_check: bool,
     pub enable_half_paren_check: bool,
     pub enable_full_paren_check: bool,
+    pub enable_code_span_check: bool,
+    pub enable_url_check: bool,
+    pub enable_heading_check: bool,
+    pub enable_heading_anchor_check: bool,
+    pub enable_link_target_check: bool,
+    pub enable_placeholder_check: bool,
+    pub enable_number_check: bool,
+    pub enable_code_block_check: bool,
+    pub enable_structure_check: bool,
+    pub cjk_latin_space: Option<CjkSpacing>,
+    pub enable_punctuation_width_check: bool,
+    pub quote_style: Option<QuoteStyle>,
+    pub enable_full_width_alnum_check: bool,
+    pub enable_comment_check: bool,
+    pub enable_trailing_whitespace_check: bool,
+    pub enable_code_comment_tweak: bool,
+    pub code_comment_header: String,
+    pub code_comment_syntax: CodeCommentSyntax,
+    pub rules: Vec<Rule>,
+    pub glossary: Option<Glossary>,
+    pub target_script: Option<TargetScript>,
+    pub untranslated_allowlist: Vec<String>,
+    pub untranslated_threshold: f64,
+    pub severity: BTreeMap<String, Severity>,
 }
 
 impl Linter {
     pub fn check(&self, target_onlys: Vec<TargetOnly>) -> Result<Vec<LintError>, Error> {
         let mut ret = Vec::new();
+        let mut anchors = HashMap::new();
 
         for target_only in target_onlys {
+            let begin = ret.len();
+            if self.enable_link_target_check {
+                ret.append(&mut self.check_link_target(&target_only.target_path, &mut anchors)?);
+            }
+            if let Some(script) = self.target_script {
+                ret.append(&mut self.check_untranslated(script, &target_only.target_path)?);
+            }
+            if self.enable_code_block_check {
+                ret.append(
+                    &mut self
+                        .check_code_block(&target_only.source_path, &target_only.target_path)?,
+                );
+            }
+            if self.enable_emphasis_check
+                || self.enable_half_paren_check
+                || self.enable_full_paren_check
+            {
+                ret.append(&mut self.check_markdown(&target_only)?);
+            }
             for line in &target_only.lines {
-                if self.enable_emphasis_check {
-                    ret.append(&mut self.check_emphasis(line, &target_only.target_path));
+                for rule in &self.rules {
+                    if rule.contexts.contains(&line.context) {
+                        ret.append(&mut self.check_rule(rule, line, &target_only.target_path));
+                    }
+                }
+
+                // Built-in rules are applied to markdown text only
+                if line.context != LineContext::Text {
+                    continue;
+                }
+                if let Some(spacing) = self.cjk_latin_space {
+                    ret.append(&mut self.check_cjk_space(spacing, line, &target_only.target_path));
+                }
+                if self.enable_punctuation_width_check {
+                    ret.append(&mut self.check_punctuation_width(line, &target_only.target_path));
+                }
+                if let Some(style) = self.quote_style {
+                    ret.append(&mut self.check_quote_style(style, line, &target_only.target_path));
+                }
+                if self.enable_full_width_alnum_check {
+                    ret.append(&mut self.check_full_width_alnum(line, &target_only.target_path));
+                }
+            }
+            for pair in &target_only.pairs {
+                if self.enable_code_span_check {
+                    ret.append(&mut self.check_preserved(
+                        pair,
+                        &target_only.target_path,
+                        extract_code_spans,
+                        str::to_owned,
+                        |text| LintErrorKind::CodeSpanNotPreserved { text },
+                    ));
+                }
+                if self.enable_url_check {
+                    ret.append(&mut self.check_preserved(
+                        pair,
+                        &target_only.target_path,
+                        extract_urls,
+                        str::to_owned,
+                        |text| LintErrorKind::UrlNotPreserved { text },
+                    ));
+                }
+                if self.enable_placeholder_check {
+                    ret.append(&mut self.check_preserved(
+                        pair,
+                        &target_only.target_path,
+                        extract_placeholders,
+                        str::to_owned,
+                        |text| LintErrorKind::PlaceholderNotPreserved { text },
+                    ));
+                }
+                if self.enable_number_check {
+                    ret.append(&mut self.check_preserved(
+                        pair,
+                        &target_only.target_path,
+                        extract_numbers,
+                        normalize_number,
+                        |text| LintErrorKind::NumberNotPreserved { text },
+                    ));
+                }
+                if self.enable_structure_check {
+                    ret.append(&mut self.check_structure(pair, &target_only.target_path));
+                    ret.append(&mut self.check_preserved(
+                        pair,
+                        &target_only.target_path,
+                        extract_footnotes,
+                        str::to_owned,
+                        |text| LintErrorKind::FootnoteNotPreserved { text },
+                    ));
+                }
+                if self.enable_heading_check {
+                    ret.append(&mut self.check_heading(pair, &target_only.target_path));
+                }
+                if self.enable_heading_anchor_check {
+                    ret.append(&mut self.check_preserved(
+                        pair,
+                        &target_only.target_path,
+                        extract_heading_anchors,
+                        str::to_owned,
+                        |text| LintErrorKind::HeadingAnchorNotPreserved { text },
+                    ));
+                }
+                if let Some(ref glossary) = self.glossary {
+                    ret.append(&mut self.check_glossary(glossary, pair, &target_only.target_path));
+                }
+            }
+
+            let errors = ret.split_off(begin);
+            ret.extend(
+                errors
+                    .into_iter()
+                    .filter(|x| {
+                        x.path != target_only.target_path
+                            || !target_only
+                                .directives
+                                .is_disabled(x.line.number, x.kind.id())
+                    })
+                    .map(|mut x| {
+                        if let Some(severity) = self.severity.get(x.kind.id()) {
+                            x.severity = *severity;
+                        }
+                        x
+                    })
+                    .filter(|x| x.severity != Severity::Off),
+            );
+        }
+
+        Ok(ret)
+    }
+
+    /// Check source files which the translation is based on
+    pub fn check_source(&self, paths: &[PathBuf]) -> Result<Vec<LintError>, Error> {
+        let mut ret = Vec::new();
+
+        for path in paths {
+            if path.extension().map(|x| x != "md").unwrap_or(true) {
+                continue;
+            }
+
+            let text = read_file(path)?;
+            let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+            let lines: Vec<_> = text
+                .lines()
+                .enumerate()
+                .map(|(i, x)| Line {
+                    number: i + 1,
+                    content: String::from(x),
+                    last_both: 0,
+                    html_comment: false,
+                    code_not_comment: false,
+                    context: LineContext::Text,
+                })
+                .collect();
+
+            if self.enable_comment_check {
+                ret.append(&mut self.check_comment(text, &lines, path));
+            }
+
+            let contexts = Matcher::get_line_contexts(text);
+            for mut line in lines {
+                line.context = contexts[line.number - 1];
+                if self.enable_trailing_whitespace_check {
+                    ret.append(&mut self.check_trailing_whitespace(&line, path));
+                }
+                for rule in &self.rules {
+                    if rule.contexts.contains(&line.context) {
+                        ret.append(&mut self.check_rule(rule, &line, path));
+                    }
+                }
+            }
+        }
+
+        Ok(ret
+            .into_iter()
+            .map(|mut x| {
+                if let Some(severity) = self.severity.get(x.kind.id()) {
+                    x.severity = *severity;
+                }
+                x
+            })
+            .filter(|x| x.severity != Severity::Off)
+            .collect())
+    }
+
+    /// Check markdown comments which are not removed correctly from the source
+    fn check_comment(&self, text: &str, lines: &[Line], path: &Path) -> Vec<LintError> {
+        let mut ret = Vec::new();
+
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        let line_index = |pos: usize| line_starts.partition_point(|x| *x <= pos) - 1;
+
+        // Comments before the malformed one are checked too
+        let (comments, error) = match comment::scan(text) {
+            Ok(x) => (x, None),
+            Err(x) => {
+                let line = &lines[x.line - 1];
+                let start = line
+                    .content
+                    .char_indices()
+                    .nth(x.column - 1)
+                    .map(|(i, _)| i)
+                    .unwrap_or(line.content.len());
+                let comments =
+                    comment::scan(&text[..line_starts[x.line - 1] + start]).unwrap_or_default();
+                (comments, Some((x.kind, line, start)))
+            }
+        };
+
+        for x in comments {
+            let begin = line_index(x.begin);
+            let end = line_index(x.end - 1);
+            let start = x.begin - line_starts[begin];
+            let line_end = text[x.end..]
+                .find('\n')
+                .map(|i| x.end + i)
+                .unwrap_or(text.len());
+
+            // Comments should occupy whole lines
+            let before = &text[line_starts[begin]..x.begin];
+            let after = &text[x.end..line_end];
+            if !before.trim().is_empty() || !after.trim().is_empty() {
+                let line = &lines[begin];
+                ret.push(LintError {
+                    kind: LintErrorKind::InlineComment,
+                    path: PathBuf::from(path),
+                    line: line.clone(),
+                    start,
+                    end: if begin == end {
+                        x.end - line_starts[begin]
+                    } else {
+                        line.content.len()
+                    },
+                    severity: Severity::Error,
+                });
+            }
+        }
+
+        if let Some((kind, line, start)) = error {
+            let (kind, len) = match kind {
+                CommentErrorKind::Unterminated => (LintErrorKind::UnterminatedComment, 4),
+                CommentErrorKind::Misordered => (LintErrorKind::MisorderedComment, 3),
+            };
+            ret.push(LintError {
+                kind,
+                path: PathBuf::from(path),
+                line: line.clone(),
+                start,
+                end: start + len,
+                severity: Severity::Error,
+            });
+        }
+
+        ret
+    }
+
+    fn check_trailing_whitespace(&self, line: &Line, path: &Path) -> Vec<LintError> {
+        let mut ret = Vec::new();
+
+        let trimmed = line.content.trim_end_matches([' ', '\t']);
+        if trimmed.len() != line.content.len() {
+            ret.push(LintError {
+                kind: LintErrorKind::TrailingWhitespace,
+                path: PathBuf::from(path),
+                line: line.clone(),
+                start: trimmed.len(),
+                end: line.content.len(),
+                severity: Severity::Error,
+            });
+        }
+
+        ret
+    }
+
+    /// Check prose text of the translation parsed as markdown
+    ///
+    /// Inline codes, code blocks and URLs are not checked.
+    fn check_markdown(&self, target_only: &TargetOnly) -> Result<Vec<LintError>, Error> {
+        let mut ret = Vec::new();
+
+        let path = &target_only.target_path;
+        let text = read_file(path)?;
+        let content = text.strip_prefix('\u{feff}').unwrap_or(&text);
+        let translated: HashSet<_> = target_only
+            .lines
+            .iter()
+            .filter(|x| x.context == LineContext::Text)
+            .map(|x| x.number)
+            .collect();
+
+        let mut line_starts = vec![0];
+        line_starts.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+        let position = |pos: usize| -> (Line, usize) {
+            let index = line_starts.partition_point(|x| *x <= pos) - 1;
+            let content = content[line_starts[index]..].lines().next().unwrap_or("");
+            let line = Line {
+                number: index + 1,
+                content: String::from(content),
+                last_both: 0,
+                html_comment: false,
+                code_not_comment: false,
+                context: LineContext::Text,
+            };
+            (line, pos - line_starts[index])
+        };
+
+        let markdown = parse_markdown(content);
+
+        for range in markdown.texts {
+            let (line, start) = position(range.start);
+            if !translated.contains(&line.number) {
+                continue;
+            }
+            let end = usize::min(start + range.len(), line.content.len());
+
+            if self.enable_half_paren_check {
+                ret.append(&mut self.check_half_paren(&line, start..end, path));
+            }
+            if self.enable_full_paren_check {
+                ret.append(&mut self.check_full_paren(&line, start..end, path));
+            }
+        }
+
+        if self.enable_emphasis_check {
+            // Delimiters which are not recognized because of the lack of spaces around them
+            for (open, close) in markdown.delimiters {
+                let kind = if content[open.clone()] == content[close.clone()] {
+                    LintErrorKind::EmphasisWithoutSpace
+                } else {
+                    LintErrorKind::EmphasisMismatch
+                };
+                let (line, start) = position(open.start);
+                let (close_line, close_start) = position(close.start);
+                let close_end = close_start + close.len();
+
+                // Emphasis across lines is pointed at both lines
+                let parts = if line.number == close_line.number {
+                    vec![(line, start, close_end)]
+                } else {
+                    let end = line.content.len();
+                    vec![(line, start, end), (close_line, 0, close_end)]
+                };
+                for (line, start, end) in parts {
+                    if translated.contains(&line.number) {
+                        ret.push(LintError {
+                            kind: kind.clone(),
+                            path: PathBuf::from(path),
+                            line,
+                            start,
+                            end,
+                            severity: Severity::Error,
+                        });
+                    }
                 }
-                if self.enable_half_paren_check {
-                    ret.append(&mut self.check_half_paren(line, &target_only.target_path));
+            }
+
+            for range in markdown.emphasis {
+                let (line, start) = position(range.start);
+                if !translated.contains(&line.number) {
+                    continue;
                 }
-                if self.enable_full_paren_check {
-                    ret.append(&mut self.check_full_paren(line, &target_only.target_path));
+
+                // check emphasis mismatch like **...* or *...**
+                let delimiter = content[range.start..].chars().next();
+                let before = content[..range.start].chars().last();
+                let after = content[range.end..].chars().next();
+                if before == delimiter || after == delimiter {
+                    let end = usize::min(start + range.len(), line.content.len());
+                    ret.push(LintError {
+                        kind: LintErrorKind::EmphasisMismatch,
+                        path: PathBuf::from(path),
+                        line,
+                        start,
+                        end,
+                        severity: Severity::Error,
+                    });
                 }
             }
         }
 
+        ret.sort_by_key(|x| (x.line.number, x.start));
         Ok(ret)
     }
 
-    fn check_emphasis(&self, line: &Line, path: &Path) -> Vec<LintError> {
+    fn check_half_paren(&self, line: &Line, range: Range<usize>, path: &Path) -> Vec<LintError> {
         let mut ret = Vec::new();
 
-        let emphasis = Regex::new(r"\*\*?[^*\s][^*]*\*\*?").unwrap();
+        for cap in HALF_PAREN.captures_iter(&line.content[range.clone()]) {
+            if !cap.get(1).unwrap().as_str().is_ascii() {
+                ret.push(LintError {
+                    kind: LintErrorKind::HalfParenWithNonAscii,
+                    path: PathBuf::from(path),
+                    line: line.clone(),
+                    start: range.start + cap.get(0).unwrap().start(),
+                    end: range.start + cap.get(0).unwrap().end(),
+                    severity: Severity::Error,
+                });
+            }
+        }
 
-        for mat in emphasis.find_iter(&line.content) {
-            let before = &line.content[..mat.start()];
-            let after = &line.content[mat.end()..];
+        ret
+    }
 
-            // check emphasis mismatch like **...* or *...**
-            if mat.as_str().starts_with("**") ^ mat.as_str().ends_with("**") {
+    fn check_full_paren(&self, line: &Line, range: Range<usize>, path: &Path) -> Vec<LintError> {
+        let mut ret = Vec::new();
+
+        for cap in FULL_PAREN.captures_iter(&line.content[range.clone()]) {
+            if cap.get(1).unwrap().as_str().is_ascii() {
                 ret.push(LintError {
-                    kind: LintErrorKind::EmphasisMismatch,
+                    kind: LintErrorKind::FullParenWithoutNonAscii,
+                    path: PathBuf::from(path),
+                    line: line.clone(),
+                    start: range.start + cap.get(0).unwrap().start(),
+                    end: range.start + cap.get(0).unwrap().end(),
+                    severity: Severity::Error,
+                });
+            }
+        }
+
+        ret
+    }
+
+    fn check_cjk_space(&self, spacing: CjkSpacing, line: &Line, path: &Path) -> Vec<LintError> {
+        let (patterns, kind) = match spacing {
+            CjkSpacing::Required => (&*CJK_SPACE_REQUIRED, LintErrorKind::CjkSpaceRequired),
+            CjkSpacing::Forbidden => (&*CJK_SPACE_FORBIDDEN, LintErrorKind::CjkSpaceForbidden),
+        };
+
+        let text = mask_code(&line.content);
+        let mut ret = Vec::new();
+        for re in patterns {
+            for mat in re.find_iter(&text) {
+                ret.push(LintError {
+                    kind: kind.clone(),
                     path: PathBuf::from(path),
                     line: line.clone(),
                     start: mat.start(),
                     end: mat.end(),
+                    severity: Severity::Error,
                 });
-            } else {
-                let before_check = before.is_empty() | before.ends_with(" ");
-                let after_check = after.is_empty() | after.starts_with(" ");
-                let check = before_check & after_check;
+            }
+        }
+        ret.sort_by_key(|x| x.start);
+
+        ret
+    }
+
+    fn check_punctuation_width(&self, line: &Line, path: &Path) -> Vec<LintError> {
+        let mut ret = Vec::new();
+
+        let text = mask_code(&line.content);
+        for cap in CJK_PUNCTUATION.captures_iter(&text) {
+            ret.push(LintError {
+                kind: LintErrorKind::HalfPunctuationWithCjk,
+                path: PathBuf::from(path),
+                line: line.clone(),
+                start: cap.get(1).unwrap().start(),
+                end: cap.get(1).unwrap().end(),
+                severity: Severity::Error,
+            });
+        }
+
+        ret
+    }
+
+    fn check_quote_style(&self, style: QuoteStyle, line: &Line, path: &Path) -> Vec<LintError> {
+        let mut ret = Vec::new();
+
+        let quote = match style {
+            QuoteStyle::Corner => &*CURLY_QUOTE,
+            QuoteStyle::Curly => &*CORNER_QUOTE,
+        };
+
+        let text = mask_code(&line.content);
+        for mat in quote.find_iter(&text) {
+            ret.push(LintError {
+                kind: LintErrorKind::QuoteStyleMismatch { style },
+                path: PathBuf::from(path),
+                line: line.clone(),
+                start: mat.start(),
+                end: mat.end(),
+                severity: Severity::Error,
+            });
+        }
+
+        ret
+    }
+
+    fn check_full_width_alnum(&self, line: &Line, path: &Path) -> Vec<LintError> {
+        let mut ret = Vec::new();
+
+        let text = mask_code(&line.content);
+        for mat in FULL_WIDTH_ALNUM.find_iter(&text) {
+            ret.push(LintError {
+                kind: LintErrorKind::FullWidthAlnum,
+                path: PathBuf::from(path),
+                line: line.clone(),
+                start: mat.start(),
+                end: mat.end(),
+                severity: Severity::Error,
+            });
+        }
+
+        ret
+    }
 
-                if !check {
+    fn check_rule(&self, rule: &Rule, line: &Line, path: &Path) -> Vec<LintError> {
+        let mut ret = Vec::new();
+
+        let kind = LintErrorKind::Custom {
+            id: rule.id.clone(),
+            message: rule.message.clone(),
+        };
+
+        match rule.mode {
+            RuleMode::MustNotMatch => {
+                for mat in rule.regex.find_iter(&line.content) {
                     ret.push(LintError {
-                        kind: LintErrorKind::EmphasisWithoutSpace,
+                        kind: kind.clone(),
                         path: PathBuf::from(path),
                         line: line.clone(),
                         start: mat.start(),
                         end: mat.end(),
+                        severity: rule.severity,
+                    });
+                }
+            }
+            RuleMode::MustMatch => {
+                // blank lines are not checked because they can't match any rule
+                if !line.content.trim().is_empty() && !rule.regex.is_match(&line.content) {
+                    ret.push(LintError {
+                        kind,
+                        path: PathBuf::from(path),
+                        line: line.clone(),
+                        start: 0,
+                        end: line.content.len(),
+                        severity: rule.severity,
                     });
                 }
             }
@@ -86,19 +750,42 @@ impl Linter {
         ret
     }
 
-    fn check_half_paren(&self, line: &Line, path: &Path) -> Vec<LintError> {
+    fn check_glossary(
+        &self,
+        glossary: &Glossary,
+        pair: &TranslationPair,
+        path: &Path,
+    ) -> Vec<LintError> {
         let mut ret = Vec::new();
 
-        let paren = Regex::new(r"\(([^)]*)\)").unwrap();
+        let translated: Vec<_> = pair.translated.iter().map(|x| x.content.as_str()).collect();
+        let translated = translated.join("\n").to_lowercase();
 
-        for cap in paren.captures_iter(&line.content) {
-            if !cap.get(1).unwrap().as_str().is_ascii() {
+        for term in &glossary.terms {
+            let found = term
+                .translations
+                .iter()
+                .any(|x| translated.contains(&x.to_lowercase()));
+            if found {
+                continue;
+            }
+
+            // Report the first occurrence only
+            let mat = pair
+                .original
+                .iter()
+                .find_map(|x| term.regex.find(&x.content).map(|mat| (x, mat)));
+            if let Some((line, mat)) = mat {
                 ret.push(LintError {
-                    kind: LintErrorKind::HalfParenWithNonAscii,
+                    kind: LintErrorKind::GlossaryMismatch {
+                        source: term.source.clone(),
+                        translations: term.translations.clone(),
+                    },
                     path: PathBuf::from(path),
                     line: line.clone(),
-                    start: cap.get(0).unwrap().start(),
-                    end: cap.get(0).unwrap().end(),
+                    start: mat.start(),
+                    end: mat.end(),
+                    severity: Severity::Error,
                 });
             }
         }
@@ -106,23 +793,1618 @@ impl Linter {
         ret
     }
 
-    fn check_full_paren(&self, line: &Line, path: &Path) -> Vec<LintError> {
+    /// Check whether the items extracted from the original are found in the translation
+    fn check_preserved(
+        &self,
+        pair: &TranslationPair,
+        path: &Path,
+        extract: fn(&str) -> Vec<(usize, usize)>,
+        normalize: fn(&str) -> String,
+        kind: fn(String) -> LintErrorKind,
+    ) -> Vec<LintError> {
         let mut ret = Vec::new();
 
-        let paren = Regex::new(r"（([^）]*)）").unwrap();
+        let mut translated = HashSet::new();
+        for line in &pair.translated {
+            for (start, end) in extract(&line.content) {
+                translated.insert(normalize(&line.content[start..end]));
+            }
+        }
 
-        for cap in paren.captures_iter(&line.content) {
-            if cap.get(1).unwrap().as_str().is_ascii() {
+        let mut reported = HashSet::new();
+        for line in &pair.original {
+            for (start, end) in extract(&line.content) {
+                let text = &line.content[start..end];
+                let normalized = normalize(text);
+                if !translated.contains(&normalized) && reported.insert(normalized) {
+                    ret.push(LintError {
+                        kind: kind(String::from(text)),
+                        path: PathBuf::from(path),
+                        line: line.clone(),
+                        start,
+                        end,
+                        severity: Severity::Error,
+                    });
+                }
+            }
+        }
+
+        ret
+    }
+
+    fn check_heading(&self, pair: &TranslationPair, path: &Path) -> Vec<LintError> {
+        let mut ret = Vec::new();
+
+        let original = get_headings(&pair.original);
+        let translated = get_headings(&pair.translated);
+
+        for i in 0..usize::max(original.len(), translated.len()) {
+            let o = original.get(i);
+            let t = translated.get(i);
+            if o.map(|x| x.1) == t.map(|x| x.1) {
+                continue;
+            }
+
+            // Point the translated heading if exists
+            let line = t.or(o).unwrap().0;
+            ret.push(LintError {
+                kind: LintErrorKind::HeadingLevelMismatch {
+                    original: o.map(|x| x.1),
+                    translated: t.map(|x| x.1),
+                },
+                path: PathBuf::from(path),
+                line: line.clone(),
+                start: 0,
+                end: line.content.len(),
+                severity: Severity::Error,
+            });
+
+            // The following headings are shifted, so only the first mismatch is reported
+            break;
+        }
+
+        ret
+    }
+
+    /// Check whether translated lines consist mostly of Latin words
+    fn check_untranslated(
+        &self,
+        script: TargetScript,
+        path: &Path,
+    ) -> Result<Vec<LintError>, Error> {
+        let mut ret = Vec::new();
+
+        // Lines with fewer words are too short to judge, like headings of proper nouns
+        const MIN_WORDS: usize = 3;
+
+        let allowlist = if self.untranslated_allowlist.is_empty() {
+            None
+        } else {
+            let words: Vec<_> = self
+                .untranslated_allowlist
+                .iter()
+                .map(|x| regex::escape(x))
+                .collect();
+            Some(Regex::new(&format!(r"\b(?:{})\b", words.join("|")))?)
+        };
+
+        // Front matter and HTML blocks are left untranslated intentionally
+        let content = read_file(path)?;
+        let contexts = Matcher::get_line_contexts(&content);
+        for line in get_visible_lines(&content) {
+            if contexts[line.number - 1] != LineContext::Text {
+                continue;
+            }
+            let mut spans = extract_code_spans(&line.content);
+            spans.append(&mut extract_urls(&line.content));
+            spans.append(&mut extract_placeholders(&line.content));
+            let text = mask(&line.content, &spans);
+            let text = HTML_TAG.replace_all(&text, " ");
+            let text = match allowlist {
+                Some(ref x) => x.replace_all(&text, " "),
+                None => text,
+            };
+
+            let words: Vec<_> = LATIN_WORD.find_iter(&text).collect();
+            let latin: usize = words.iter().map(|x| x.as_str().len()).sum();
+            let target = text
+                .chars()
+                .filter(|x| is_target_script(script, *x))
+                .count();
+            if words.len() < MIN_WORDS {
+                continue;
+            }
+
+            if latin as f64 / (latin + target) as f64 > self.untranslated_threshold {
                 ret.push(LintError {
-                    kind: LintErrorKind::FullParenWithoutNonAscii,
+                    kind: LintErrorKind::Untranslated,
                     path: PathBuf::from(path),
                     line: line.clone(),
-                    start: cap.get(0).unwrap().start(),
-                    end: cap.get(0).unwrap().end(),
+                    start: 0,
+                    end: line.content.len(),
+                    severity: Severity::Error,
                 });
             }
         }
 
+        Ok(ret)
+    }
+
+    /// Check whether lists, tables and blockquotes in the translation have the same structure
+    fn check_structure(&self, pair: &TranslationPair, path: &Path) -> Vec<LintError> {
+        let mut ret = Vec::new();
+
+        let original = get_structure(&pair.original);
+        let translated = get_structure(&pair.translated);
+
+        let mut kinds = Vec::new();
+        if original.lists != translated.lists {
+            kinds.push((
+                translated.list_line,
+                LintErrorKind::ListMismatch {
+                    original: original.lists,
+                    translated: translated.lists,
+                },
+            ));
+        }
+        if original.tables != translated.tables {
+            kinds.push((
+                translated.table_line,
+                LintErrorKind::TableMismatch {
+                    original: original.tables,
+                    translated: translated.tables,
+                },
+            ));
+        }
+        if original.blockquote != translated.blockquote {
+            kinds.push((
+                translated.blockquote_line,
+                LintErrorKind::BlockquoteMismatch {
+                    original: original.blockquote,
+                    translated: translated.blockquote,
+                },
+            ));
+        }
+
+        for (line, kind) in kinds {
+            // Point the first translated line if the block is not found in the translation
+            let line = line.or_else(|| pair.translated.first()).unwrap();
+            ret.push(LintError {
+                kind,
+                path: PathBuf::from(path),
+                line: line.clone(),
+                start: 0,
+                end: line.content.len(),
+                severity: Severity::Error,
+            });
+        }
+
         ret
     }
+
+    /// Check whether relative links point existing files and headings in the target tree
+    fn check_link_target(
+        &self,
+        path: &Path,
+        anchors: &mut HashMap<PathBuf, HashSet<String>>,
+    ) -> Result<Vec<LintError>, Error> {
+        let mut ret = Vec::new();
+
+        let content = read_file(path)?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+
+        for line in get_visible_lines(&content) {
+            let masked = mask(&line.content, &extract_code_spans(&line.content));
+            for (start, end) in extract_urls(&masked) {
+                let url = &line.content[start..end];
+                if URL_SCHEME.is_match(url) || url.starts_with('/') {
+                    continue;
+                }
+
+                let (file, fragment) = match url.find('#') {
+                    Some(i) => (&url[..i], Some(&url[i + 1..])),
+                    None => (url, None),
+                };
+
+                let linked = if file.is_empty() {
+                    PathBuf::from(path)
+                } else {
+                    let mut linked = base.join(percent_decode(file));
+                    // mdBook translates links to `.md` into `.html`
+                    if !linked.exists() && linked.extension().map(|x| x == "html").unwrap_or(false)
+                    {
+                        linked.set_extension("md");
+                    }
+                    linked
+                };
+
+                let kind = if !linked.exists() {
+                    Some(LintErrorKind::LinkTargetNotFound {
+                        text: String::from(url),
+                    })
+                } else if let (Some(fragment), true) = (
+                    fragment,
+                    linked.extension().map(|x| x == "md").unwrap_or(false),
+                ) {
+                    if !anchors.contains_key(&linked) {
+                        let content = read_file(&linked)?;
+                        anchors.insert(linked.clone(), get_anchors(&content));
+                    }
+                    if anchors[&linked].contains(&percent_decode(fragment)) {
+                        None
+                    } else {
+                        Some(LintErrorKind::LinkAnchorNotFound {
+                            text: String::from(url),
+                        })
+                    }
+                } else {
+                    None
+                };
+
+                if let Some(kind) = kind {
+                    ret.push(LintError {
+                        kind,
+                        path: PathBuf::from(path),
+                        line: line.clone(),
+                        start,
+                        end,
+                        severity: Severity::Error,
+                    });
+                }
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Check whether code blocks in the translation are the same as the original except comments
+    fn check_code_block(
+        &self,
+        source_path: &Path,
+        target_path: &Path,
+    ) -> Result<Vec<LintError>, Error> {
+        let mut ret = Vec::new();
+
+        let target = read_file(target_path)?;
+        let original = get_code_blocks(&read_file(source_path)?);
+        let translated = get_code_blocks(&target);
+
+        for i in 0..usize::max(original.len(), translated.len()) {
+            let (o, t) = match (original.get(i), translated.get(i)) {
+                (Some(o), Some(t)) => (o, t),
+                (Some(o), None) => {
+                    // Point the end of the last translated block or the end of the file
+                    let line = match i.checked_sub(1) {
+                        Some(x) => translated[x].end.clone(),
+                        None => Line {
+                            number: usize::max(target.lines().count(), 1),
+                            content: String::from(target.lines().last().unwrap_or_default()),
+                            last_both: 0,
+                            html_comment: false,
+                            code_not_comment: false,
+                            context: LineContext::Text,
+                        },
+                    };
+                    ret.push(LintError {
+                        kind: LintErrorKind::CodeBlockMissing {
+                            original: format!(
+                                "{}:{}",
+                                source_path.to_string_lossy(),
+                                o.fence.number
+                            ),
+                        },
+                        path: PathBuf::from(target_path),
+                        end: line.content.len(),
+                        line,
+                        start: 0,
+                        severity: Severity::Error,
+                    });
+                    break;
+                }
+                (None, Some(t)) => {
+                    ret.push(LintError {
+                        kind: LintErrorKind::CodeBlockGarbage,
+                        path: PathBuf::from(target_path),
+                        line: t.fence.clone(),
+                        start: 0,
+                        end: t.fence.content.len(),
+                        severity: Severity::Error,
+                    });
+                    break;
+                }
+                (None, None) => break,
+            };
+
+            if o.info != t.info {
+                ret.push(LintError {
+                    kind: LintErrorKind::CodeBlockInfoMismatch {
+                        original: o.info.clone(),
+                        translated: t.info.clone(),
+                    },
+                    path: PathBuf::from(target_path),
+                    line: t.fence.clone(),
+                    start: 0,
+                    end: t.fence.content.len(),
+                    severity: Severity::Error,
+                });
+            }
+
+            let o_code = self.strip_code_block(o, false);
+            let t_code = self.strip_code_block(t, true);
+
+            for j in 0..usize::max(o_code.len(), t_code.len()) {
+                let o_line = o_code.get(j);
+                let t_line = t_code.get(j);
+                if o_line.map(|x| &x.1) == t_line.map(|x| &x.1) {
+                    continue;
+                }
+
+                // Point the closing fence if the translated block is shorter
+                let line = t_line.map(|x| x.0).unwrap_or(&t.end);
+                ret.push(LintError {
+                    kind: LintErrorKind::CodeBlockMismatch {
+                        original: o_line.map(|x| x.0.content.clone()).unwrap_or_default(),
+                    },
+                    path: PathBuf::from(target_path),
+                    line: line.clone(),
+                    start: 0,
+                    end: line.content.len(),
+                    severity: Severity::Error,
+                });
+                break;
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /// Strip code comments of each line in `block` by the syntax of the language
+    fn strip_code_block<'a>(
+        &self,
+        block: &'a CodeBlock,
+        translated: bool,
+    ) -> Vec<(&'a Line, String)> {
+        let mut scanner = CommentScanner::new(self.code_comment_syntax.get(&block.info));
+        block
+            .lines
+            .iter()
+            .filter_map(|x| {
+                self.strip_code_comment(&x.content, translated, &mut scanner)
+                    .map(|y| (x, y))
+            })
+            .collect()
+    }
+
+    /// Strip code comments to compare code only
+    ///
+    /// Returns `None` if the line has comment only.
+    fn strip_code_comment(
+        &self,
+        line: &str,
+        translated: bool,
+        scanner: &mut CommentScanner,
+    ) -> Option<String> {
+        let header = scanner.syntax().header_or(&self.code_comment_header);
+        let line = match line.strip_prefix(header) {
+            Some(x) if translated && self.enable_code_comment_tweak => x,
+            _ => line,
+        };
+        let (code, comment) = scanner.strip(line);
+        let code = code.trim_end();
+        if code.trim().is_empty() && comment {
+            None
+        } else {
+            Some(String::from(code))
+        }
+    }
+}
+
+/// Regex class of CJK characters
+const CJK: &str = r"[\p{Han}\p{Hiragana}\p{Katakana}\p{Hangul}]";
+
+fn regex(pattern: &str) -> Regex {
+    Regex::new(pattern).unwrap()
+}
+
+static HALF_PAREN: LazyLock<Regex> = LazyLock::new(|| regex(r"\(([^)]*)\)"));
+static FULL_PAREN: LazyLock<Regex> = LazyLock::new(|| regex(r"（([^）]*)）"));
+static CJK_SPACE_REQUIRED: LazyLock<[Regex; 2]> = LazyLock::new(|| {
+    [
+        regex(&format!("{}[A-Za-z0-9]", CJK)),
+        regex(&format!("[A-Za-z0-9]{}", CJK)),
+    ]
+});
+static CJK_SPACE_FORBIDDEN: LazyLock<[Regex; 2]> = LazyLock::new(|| {
+    [
+        regex(&format!("{} +[A-Za-z0-9]", CJK)),
+        regex(&format!("[A-Za-z0-9] +{}", CJK)),
+    ]
+});
+static CJK_PUNCTUATION: LazyLock<Regex> = LazyLock::new(|| regex(&format!("{}([,.:;!?])", CJK)));
+static CURLY_QUOTE: LazyLock<Regex> = LazyLock::new(|| regex(r"“[^”]*”"));
+static CORNER_QUOTE: LazyLock<Regex> = LazyLock::new(|| regex(r"「[^」]*」"));
+static FULL_WIDTH_ALNUM: LazyLock<Regex> = LazyLock::new(|| regex(r"[０-９Ａ-Ｚａ-ｚ]+"));
+static LATIN_WORD: LazyLock<Regex> = LazyLock::new(|| regex(r"[A-Za-z]+(?:['’-][A-Za-z]+)*"));
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| regex(r"</?[a-zA-Z][^>]*>"));
+static URL_SCHEME: LazyLock<Regex> = LazyLock::new(|| regex(r"^[a-zA-Z][a-zA-Z0-9+.-]*:"));
+static LIST_ITEM: LazyLock<Regex> = LazyLock::new(|| regex(r"^(\s*)(?:[-*+]|[0-9]+[.)])\s"));
+static THEMATIC_BREAK: LazyLock<Regex> =
+    LazyLock::new(|| regex(r"^ {0,3}(?:(?:\*[ \t]*){3,}|(?:-[ \t]*){3,}|(?:_[ \t]*){3,})$"));
+static TABLE_ALIGNMENT: LazyLock<Regex> =
+    LazyLock::new(|| regex(r"^\s*\|?\s*:?-+:?\s*(?:\|\s*:?-+:?\s*)*\|?\s*$"));
+static BLOCKQUOTE: LazyLock<Regex> = LazyLock::new(|| regex(r"^\s*((?:>\s*)+)"));
+static EXPLICIT_ANCHOR: LazyLock<Regex> = LazyLock::new(|| regex(r"\s*\{#([^}\s]+)\}\s*$"));
+static HTML_ANCHOR: LazyLock<Regex> =
+    LazyLock::new(|| regex(r#"<a\s[^>]*(?:id|name)\s*=\s*"([^"]+)""#));
+static INLINE_LINK: LazyLock<Regex> = LazyLock::new(|| regex(r"!?\[([^\]]*)\]\([^)]*\)"));
+static ATX_HEADING: LazyLock<Regex> = LazyLock::new(|| regex(r"^ {0,3}(#{1,6})(\s|$)"));
+static SETEXT_UNDERLINE: LazyLock<Regex> = LazyLock::new(|| regex(r"^ {0,3}(=+|-+)\s*$"));
+static HEADING_ANCHOR: LazyLock<Regex> = LazyLock::new(|| regex(r"\{#[^}\s]+\}\s*$"));
+static CODE_SPAN: LazyLock<Regex> = LazyLock::new(|| regex(r"`[^`]+`"));
+static LINK_URL: LazyLock<Regex> = LazyLock::new(|| regex(r"\]\(\s*<?([^)\s>]+)>?(?:\s+[^)]*)?\)"));
+static REFERENCE_URL: LazyLock<Regex> = LazyLock::new(|| regex(r"^\s*\[[^\]]+\]:\s*<?([^\s>]+)"));
+static AUTOLINK_URL: LazyLock<Regex> =
+    LazyLock::new(|| regex(r"<([a-zA-Z][a-zA-Z0-9+.-]*:[^\s>]+)>"));
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| regex(r"\{\{#[^}]+\}\}"));
+static FOOTNOTE: LazyLock<Regex> = LazyLock::new(|| regex(r"\[\^[^\]\s]+\]"));
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| regex(r"[0-9０-９]+(?:[.,．，][0-9０-９]+)*"));
+
+fn is_target_script(script: TargetScript, c: char) -> bool {
+    let han = matches!(c, '\u{3005}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' | '\u{20000}'..='\u{2FA1F}');
+    match script {
+        TargetScript::Japanese => {
+            han || matches!(c, '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}')
+        }
+        TargetScript::Han => han,
+        TargetScript::Hangul => {
+            matches!(c, '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}')
+        }
+        TargetScript::Cyrillic => matches!(c, '\u{0400}'..='\u{052F}'),
+    }
+}
+
+#[derive(Default)]
+struct Structure<'a> {
+    /// Nesting depth of each list item
+    lists: Vec<usize>,
+    /// Alignment of each column in tables
+    tables: Vec<String>,
+    /// Maximum depth of blockquotes
+    blockquote: usize,
+    list_line: Option<&'a Line>,
+    table_line: Option<&'a Line>,
+    blockquote_line: Option<&'a Line>,
+}
+
+/// Get block structure out of code blocks
+fn get_structure(lines: &[Line]) -> Structure<'_> {
+    let mut ret = Structure::default();
+    let mut indents = Vec::new();
+    let mut fences = Fences::default();
+    for line in lines {
+        let content = &line.content;
+        if fences.next(content) != CodeLine::Text {
+            continue;
+        }
+
+        if let Some(cap) = BLOCKQUOTE.captures(content) {
+            let depth = cap.get(1).unwrap().as_str().matches('>').count();
+            ret.blockquote = usize::max(ret.blockquote, depth);
+            ret.blockquote_line.get_or_insert(line);
+        }
+        let content = BLOCKQUOTE.replace(content, "");
+
+        // Thematic break like `* * *` is not a list item
+        let list_item = if THEMATIC_BREAK.is_match(&content) {
+            None
+        } else {
+            LIST_ITEM.captures(&content)
+        };
+        if let Some(cap) = list_item {
+            let indent = cap.get(1).unwrap().as_str().len();
+            while indents.last().map(|x| *x > indent).unwrap_or(false) {
+                indents.pop();
+            }
+            if indents.last().map(|x| *x < indent).unwrap_or(true) {
+                indents.push(indent);
+            }
+            ret.lists.push(indents.len() - 1);
+            ret.list_line.get_or_insert(line);
+        }
+
+        if content.contains('|') && TABLE_ALIGNMENT.is_match(&content) {
+            let cells = content.trim().trim_start_matches('|').trim_end_matches('|');
+            for cell in cells.split('|') {
+                let cell = cell.trim();
+                let align = match (cell.starts_with(':'), cell.ends_with(':')) {
+                    (true, true) => "center",
+                    (true, false) => "left",
+                    (false, true) => "right",
+                    (false, false) => "none",
+                };
+                ret.tables.push(String::from(align));
+            }
+            ret.table_line.get_or_insert(line);
+        }
+    }
+    ret
+}
+
+struct CodeBlock {
+    fence: Line,
+    end: Line,
+    info: String,
+    lines: Vec<Line>,
+}
+
+/// Get fenced and indented code blocks which are not in markdown comments
+fn get_code_blocks(content: &str) -> Vec<CodeBlock> {
+    let comments = comment::track(content);
+    let mut fences = Fences::default();
+    let mut ret = Vec::new();
+    let mut code_block: Option<CodeBlock> = None;
+    let mut fenced = false;
+    for (i, x) in content.lines().enumerate() {
+        if !fenced && comments[i].commented && !comments[i].translated {
+            continue;
+        }
+        let line = Line {
+            number: i + 1,
+            content: String::from(x),
+            last_both: 0,
+            html_comment: false,
+            code_not_comment: false,
+            context: LineContext::Text,
+        };
+        let code = fences.next(x);
+        fenced = matches!(code, CodeLine::Open(_) | CodeLine::Fenced);
+        match code {
+            CodeLine::Open(info) => {
+                ret.extend(code_block.take());
+                code_block = Some(CodeBlock {
+                    fence: line.clone(),
+                    end: line,
+                    info,
+                    lines: Vec::new(),
+                });
+            }
+            CodeLine::Fenced => {
+                if let Some(block) = &mut code_block {
+                    block.lines.push(line);
+                }
+            }
+            CodeLine::Close => {
+                if let Some(mut block) = code_block.take() {
+                    block.end = line;
+                    ret.push(block);
+                }
+            }
+            // Indented code block has no fence, so the first and the last lines are used
+            CodeLine::Indented => match &mut code_block {
+                Some(block) => {
+                    block.end = line.clone();
+                    block.lines.push(line);
+                }
+                None => {
+                    code_block = Some(CodeBlock {
+                        fence: line.clone(),
+                        end: line.clone(),
+                        info: String::new(),
+                        lines: vec![line],
+                    });
+                }
+            },
+            CodeLine::Text if !x.trim().is_empty() => ret.extend(code_block.take()),
+            CodeLine::Text => (),
+        }
+    }
+    // Unclosed fenced code block is ignored
+    if !fenced {
+        ret.extend(code_block);
+    }
+    ret
+}
+
+/// Byte ranges of prose text and emphasis in markdown
+struct Markdown {
+    /// Adjacent text events are merged, so unrecognized emphasis delimiters are included in the text
+    texts: Vec<Range<usize>>,
+    emphasis: Vec<Range<usize>>,
+    /// Pairs of emphasis delimiters like `**` which are left as text in the same block
+    delimiters: Vec<(Range<usize>, Range<usize>)>,
+}
+
+fn parse_markdown(content: &str) -> Markdown {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES;
+
+    let mut ret = Markdown {
+        texts: Vec::new(),
+        emphasis: Vec::new(),
+        delimiters: Vec::new(),
+    };
+    let mut block = 0;
+    let mut code_block = false;
+    let mut autolink = false;
+    for (event, range) in Parser::new_ext(content, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Emphasis) | Event::Start(Tag::Strong) => ret.emphasis.push(range),
+            Event::Start(Tag::CodeBlock(_)) => code_block = true,
+            Event::End(TagEnd::CodeBlock) => code_block = false,
+            Event::Start(Tag::Link {
+                link_type: LinkType::Autolink | LinkType::Email,
+                ..
+            }) => autolink = true,
+            Event::End(TagEnd::Link) => autolink = false,
+            Event::Text(_) if !code_block && !autolink => match ret.texts.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ret.texts.push(range),
+            },
+            // Delimiters are paired in the same block only
+            Event::Start(x) if !is_inline(&x.to_end()) => {
+                pair_delimiters(content, &ret.texts[block..], &mut ret.delimiters);
+                block = ret.texts.len();
+            }
+            Event::End(x) if !is_inline(&x) => {
+                pair_delimiters(content, &ret.texts[block..], &mut ret.delimiters);
+                block = ret.texts.len();
+            }
+            _ => (),
+        }
+    }
+    pair_delimiters(content, &ret.texts[block..], &mut ret.delimiters);
+    ret
+}
+
+fn is_inline(tag: &TagEnd) -> bool {
+    matches!(
+        tag,
+        TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link | TagEnd::Image
+    )
+}
+
+/// Pair runs of `*` and `_` in the text of a block
+///
+/// A run can be opened if it is followed by non-whitespace,
+/// and closed if it is preceded by non-whitespace.
+/// Escaped runs and intraword `_` like `snake_case` are not delimiters.
+fn pair_delimiters(
+    content: &str,
+    texts: &[Range<usize>],
+    pairs: &mut Vec<(Range<usize>, Range<usize>)>,
+) {
+    let mut opened: Vec<Range<usize>> = Vec::new();
+    let runs = texts.iter().flat_map(|range| {
+        let mut chars = content[range.clone()].char_indices().peekable();
+        std::iter::from_fn(move || {
+            while let Some((i, c)) = chars.next() {
+                if c == '*' || c == '_' {
+                    let mut len = 1;
+                    while chars.next_if(|x| x.1 == c).is_some() {
+                        len += 1;
+                    }
+                    return Some((c, range.start + i..range.start + i + len));
+                }
+            }
+            None
+        })
+    });
+    for (c, run) in runs {
+        let before = content[..run.start].chars().last();
+        let after = content[run.end..].chars().next();
+        let intraword = before.is_some_and(|x| x.is_ascii_alphanumeric())
+            && after.is_some_and(|x| x.is_ascii_alphanumeric());
+        if before == Some('\\') || (c == '_' && intraword) {
+            continue;
+        }
+
+        let can_open = after.is_some_and(|x| !x.is_whitespace());
+        let can_close = before.is_some_and(|x| !x.is_whitespace());
+        let opener = opened
+            .iter()
+            .rposition(|x| content[x.clone()].starts_with(c));
+        match opener {
+            Some(j) if can_close => {
+                pairs.push((opened[j].clone(), run));
+                opened.truncate(j);
+            }
+            _ if can_open => opened.push(run),
+            _ => (),
+        }
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, Error> {
+    let mut ret = String::new();
+    let mut reader = BufReader::new(
+        File::open(path).with_context(|| format!("Failed to open '{}'", path.to_string_lossy()))?,
+    );
+    reader
+        .read_to_string(&mut ret)
+        .with_context(|| format!("Failed to read '{}'", path.to_string_lossy()))?;
+    Ok(ret)
+}
+
+/// Get lines which are rendered by mdBook ( not in markdown comments and code blocks )
+fn get_visible_lines(content: &str) -> Vec<Line> {
+    let comments = comment::track(content);
+    let mut fences = Fences::default();
+    let mut ret = Vec::new();
+    let mut fenced = false;
+    for (i, x) in content.lines().enumerate() {
+        if !fenced && comments[i].commented && !comments[i].translated {
+            continue;
+        }
+        let code = fences.next(x);
+        fenced = matches!(code, CodeLine::Open(_) | CodeLine::Fenced);
+        if code != CodeLine::Text {
+            continue;
+        }
+        ret.push(Line {
+            number: i + 1,
+            content: String::from(x),
+            last_both: 0,
+            html_comment: false,
+            code_not_comment: false,
+            context: LineContext::Text,
+        });
+    }
+    ret
+}
+
+/// Get heading anchors which are generated by mdBook
+fn get_anchors(content: &str) -> HashSet<String> {
+    let lines = get_visible_lines(content);
+    let mut ret = HashSet::new();
+    for (line, _) in get_headings(&lines) {
+        let text = line.content.trim().trim_start_matches('#').trim();
+        if let Some(cap) = EXPLICIT_ANCHOR.captures(text) {
+            ret.insert(String::from(cap.get(1).unwrap().as_str()));
+            continue;
+        }
+
+        // Duplicated anchors are suffixed by `-1`, `-2`, ...
+        let id = normalize_id(text.trim_end_matches('#').trim());
+        let mut unique = id.clone();
+        let mut i = 1;
+        while ret.contains(&unique) {
+            unique = format!("{}-{}", id, i);
+            i += 1;
+        }
+        ret.insert(unique);
+    }
+    for line in &lines {
+        for cap in HTML_ANCHOR.captures_iter(&line.content) {
+            ret.insert(String::from(cap.get(1).unwrap().as_str()));
+        }
+    }
+    ret
+}
+
+/// Normalize heading text to anchor like mdBook
+fn normalize_id(text: &str) -> String {
+    let text = INLINE_LINK.replace_all(text, "$1");
+    text.chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                Some(c.to_ascii_lowercase())
+            } else if c.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes in URL
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut ret = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .filter(|x| x.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|x| std::str::from_utf8(x).ok())
+            .and_then(|x| u8::from_str_radix(x, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(x)) => {
+                ret.push(x);
+                i += 3;
+            }
+            (x, _) => {
+                ret.push(x);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&ret).into_owned()
+}
+
+/// Mask inline code spans and URLs which are not prose
+fn mask_code(text: &str) -> String {
+    let mut spans = extract_code_spans(text);
+    spans.append(&mut extract_urls(text));
+    mask(text, &spans)
+}
+
+/// Replace the spans with spaces to keep byte offsets
+fn mask(text: &str, spans: &[(usize, usize)]) -> String {
+    let mut ret = String::from(text);
+    for &(start, end) in spans {
+        ret.replace_range(start..end, &" ".repeat(end - start));
+    }
+    ret
+}
+
+/// Get ATX and setext headings with the level
+fn get_headings(lines: &[Line]) -> Vec<(&Line, usize)> {
+    let mut ret = Vec::new();
+    let mut prev: Option<&Line> = None;
+    for line in lines {
+        if let Some(cap) = ATX_HEADING.captures(&line.content) {
+            ret.push((line, cap.get(1).unwrap().as_str().len()));
+        } else if let Some(cap) = SETEXT_UNDERLINE.captures(&line.content) {
+            let underline_of_text = prev
+                .map(|x| x.number + 1 == line.number && !x.content.trim().is_empty())
+                .unwrap_or(false);
+            if underline_of_text {
+                let level = if cap.get(1).unwrap().as_str().starts_with('=') {
+                    1
+                } else {
+                    2
+                };
+                ret.push((prev.unwrap(), level));
+            }
+        }
+        prev = Some(line);
+    }
+    ret
+}
+
+/// Extract explicit heading anchors like `{#anchor}`
+fn extract_heading_anchors(text: &str) -> Vec<(usize, usize)> {
+    if !ATX_HEADING.is_match(text) {
+        return Vec::new();
+    }
+    HEADING_ANCHOR
+        .find_iter(text)
+        .map(|x| (x.start(), x.start() + x.as_str().trim_end().len()))
+        .collect()
+}
+
+/// Extract inline code spans like `` `foo` ``
+fn extract_code_spans(text: &str) -> Vec<(usize, usize)> {
+    CODE_SPAN
+        .find_iter(text)
+        .map(|x| (x.start(), x.end()))
+        .collect()
+}
+
+/// Extract link URLs, image paths and reference definitions
+fn extract_urls(text: &str) -> Vec<(usize, usize)> {
+    let mut ret = Vec::new();
+    for re in [&*LINK_URL, &*REFERENCE_URL, &*AUTOLINK_URL] {
+        for cap in re.captures_iter(text) {
+            let url = cap.get(1).unwrap();
+            ret.push((url.start(), url.end()));
+        }
+    }
+    ret.sort_unstable();
+    ret
+}
+
+/// Extract mdBook placeholders like `{{#include file.rs}}`
+fn extract_placeholders(text: &str) -> Vec<(usize, usize)> {
+    PLACEHOLDER
+        .find_iter(text)
+        .map(|x| (x.start(), x.end()))
+        .collect()
+}
+
+/// Extract footnote references and definitions like `[^note]`
+fn extract_footnotes(text: &str) -> Vec<(usize, usize)> {
+    FOOTNOTE
+        .find_iter(text)
+        .map(|x| (x.start(), x.end()))
+        .collect()
+}
+
+/// Extract numbers and version strings out of code spans, URLs and placeholders
+fn extract_numbers(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = extract_code_spans(text);
+    spans.append(&mut extract_urls(text));
+    spans.append(&mut extract_placeholders(text));
+    let masked = mask(text, &spans);
+
+    let mut ret = Vec::new();
+    for mat in NUMBER.find_iter(&masked) {
+        // Skip identifiers like `x86_64` and `utf8`, but keep versions like `v1.0`
+        let mut before = masked[..mat.start()].chars().rev();
+        let part_of_word = match (before.next(), before.next()) {
+            (Some('v'), x) | (Some('V'), x) => {
+                x.map(|x| x.is_ascii_alphanumeric()).unwrap_or(false)
+            }
+            (Some(x), _) => x.is_ascii_alphanumeric() || x == '_',
+            _ => false,
+        };
+        if !part_of_word {
+            ret.push((mat.start(), mat.end()));
+        }
+    }
+    ret
+}
+
+/// Normalize full-width digits and digit group separators
+fn normalize_number(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '０'..='９' => std::char::from_u32(c as u32 - '０' as u32 + '0' as u32),
+            '．' => Some('.'),
+            ',' | '，' => None,
+            _ => Some(c),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::directive::Directives;
+    use crate::util::TempDir;
+
+    fn line(content: &str) -> Line {
+        numbered_line(1, content)
+    }
+
+    fn numbered_line(number: usize, content: &str) -> Line {
+        Line {
+            number,
+            content: String::from(content),
+            last_both: 0,
+            html_comment: false,
+            code_not_comment: false,
+            context: LineContext::Text,
+        }
+    }
+
+    fn rule(pattern: &str, mode: RuleMode) -> Rule {
+        Rule::new(&ConfigRule {
+            id: String::from("test"),
+            pattern: String::from(pattern),
+            mode,
+            message: String::from("test message"),
+            severity: Severity::Warn,
+            contexts: vec![LineContext::Text],
+        })
+        .unwrap()
+    }
+
+    fn linter(rules: Vec<Rule>) -> Linter {
+        Linter {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_rule_must_not_match() {
+        let rule = rule("コンピュータ[^ー]", RuleMode::MustNotMatch);
+        let linter = linter(Vec::new());
+        let ret = linter.check_rule(&rule, &line("コンピュータを使う"), Path::new("a.md"));
+        assert_eq!(ret.len(), 1);
+        assert_eq!(ret[0].start, 0);
+        assert_eq!(ret[0].end, "コンピュータを".len());
+        assert_eq!(ret[0].severity, Severity::Warn);
+
+        let ret = linter.check_rule(&rule, &line("コンピューターを使う"), Path::new("a.md"));
+        assert!(ret.is_empty());
+    }
+
+    #[test]
+    fn test_check_context() {
+        let mut code_rule = rule("unwrap", RuleMode::MustNotMatch);
+        code_rule.contexts = vec![LineContext::CodeBlock];
+        let mut linter = linter(vec![
+            rule("コンピュータ[^ー]", RuleMode::MustNotMatch),
+            code_rule,
+        ]);
+        linter.enable_full_width_alnum_check = true;
+
+        let mut code = line("// コンピュータを使う Ａ x.unwrap()");
+        code.context = LineContext::CodeBlock;
+        let target_only = TargetOnly {
+            source_path: PathBuf::from("source.md"),
+            target_path: PathBuf::from("a.md"),
+            lines: vec![code],
+            pairs: Vec::new(),
+            directives: Default::default(),
+        };
+        let ret = linter.check(vec![target_only]).unwrap();
+        assert_eq!(ret.len(), 1);
+        assert_eq!(&ret[0].line.content[ret[0].start..ret[0].end], "unwrap");
+    }
+
+    #[test]
+    fn test_check_severity() {
+        let mut linter = linter(Vec::new());
+        linter.enable_full_width_alnum_check = true;
+        linter.enable_punctuation_width_check = true;
+        linter
+            .severity
+            .insert(String::from("full-width-alnum"), Severity::Warn);
+        linter
+            .severity
+            .insert(String::from("punctuation-width"), Severity::Off);
+
+        let target_only = TargetOnly {
+            source_path: PathBuf::from("source.md"),
+            target_path: PathBuf::from("a.md"),
+            lines: vec![line("ＲＵＳＴは,")],
+            pairs: Vec::new(),
+            directives: Default::default(),
+        };
+        let ret = linter.check(vec![target_only]).unwrap();
+        assert_eq!(ret.len(), 1);
+        assert!(matches!(ret[0].kind, LintErrorKind::FullWidthAlnum));
+        assert_eq!(ret[0].severity, Severity::Warn);
+    }
+
+    #[test]
+    fn test_check_rule_must_match() {
+        let rule = rule("[。：]$", RuleMode::MustMatch);
+        let linter = linter(Vec::new());
+        let ret = linter.check_rule(&rule, &line("文末"), Path::new("a.md"));
+        assert_eq!(ret.len(), 1);
+        assert!(matches!(&ret[0].kind, LintErrorKind::Custom { id, .. } if id == "test"));
+
+        let ret = linter.check_rule(&rule, &line("文末。"), Path::new("a.md"));
+        assert!(ret.is_empty());
+        let ret = linter.check_rule(&rule, &line(""), Path::new("a.md"));
+        assert!(ret.is_empty());
+    }
+
+    #[test]
+    fn test_check_glossary() {
+        let glossary = Glossary::from_toml(r#""borrow checker" = "借用チェッカー""#).unwrap();
+        let linter = linter(Vec::new());
+        let pair = TranslationPair {
+            original: vec![numbered_line(2, "The borrow checker rejects it.")],
+            translated: vec![numbered_line(4, "借用検査器が拒否します。")],
+        };
+        let ret = linter.check_glossary(&glossary, &pair, Path::new("a.md"));
+        assert_eq!(ret.len(), 1);
+        assert_eq!(ret[0].line.number, 2);
+        assert_eq!(ret[0].start, 4);
+        assert_eq!(ret[0].end, 18);
+
+        let pair = TranslationPair {
+            original: vec![numbered_line(2, "The borrow checker rejects it.")],
+            translated: vec![numbered_line(4, "借用チェッカーが拒否します。")],
+        };
+        let ret = linter.check_glossary(&glossary, &pair, Path::new("a.md"));
+        assert!(ret.is_empty());
+    }
+
+    #[test]
+    fn test_check_preserved() {
+        let linter = linter(Vec::new());
+        let pair = TranslationPair {
+            original: vec![numbered_line(
+                2,
+                "Call `foo` and `bar`, see [the book](../ch01.md#intro) and ![img](img/a.png).",
+            )],
+            translated: vec![numbered_line(
+                4,
+                "`foo` と `バー` を呼ぶ。[本](../ch01.md#intro) と ![画像](img/b.png) を参照。",
+            )],
+        };
+        let ret = linter.check_preserved(
+            &pair,
+            Path::new("a.md"),
+            extract_code_spans,
+            str::to_owned,
+            |text| LintErrorKind::CodeSpanNotPreserved { text },
+        );
+        assert_eq!(ret.len(), 1);
+        assert!(
+            matches!(&ret[0].kind, LintErrorKind::CodeSpanNotPreserved { text } if text == "`bar`")
+        );
+
+        let ret = linter.check_preserved(
+            &pair,
+            Path::new("a.md"),
+            extract_urls,
+            str::to_owned,
+            |text| LintErrorKind::UrlNotPreserved { text },
+        );
+        assert_eq!(ret.len(), 1);
+        assert!(
+            matches!(&ret[0].kind, LintErrorKind::UrlNotPreserved { text } if text == "img/a.png")
+        );
+    }
+
+    #[test]
+    fn test_check_preserved_number() {
+        let linter = linter(Vec::new());
+        let pair = TranslationPair {
+            original: vec![
+                numbered_line(2, "Rust 1.40 supports x86_64 with 1,000 items in 3 steps."),
+                numbered_line(3, "{{#include ../listings/ch02/src/main.rs:here}}"),
+            ],
+            translated: vec![
+                numbered_line(
+                    5,
+                    "Rust １.４０ は x86_64 で 1000 項目を 4 ステップでサポートします。",
+                ),
+                numbered_line(6, "{{#include ../listings/ch02/src/mian.rs:here}}"),
+            ],
+        };
+        let ret = linter.check_preserved(
+            &pair,
+            Path::new("a.md"),
+            extract_numbers,
+            normalize_number,
+            |text| LintErrorKind::NumberNotPreserved { text },
+        );
+        assert_eq!(ret.len(), 1);
+        assert!(matches!(&ret[0].kind, LintErrorKind::NumberNotPreserved { text } if text == "3"));
+
+        let ret = linter.check_preserved(
+            &pair,
+            Path::new("a.md"),
+            extract_placeholders,
+            str::to_owned,
+            |text| LintErrorKind::PlaceholderNotPreserved { text },
+        );
+        assert_eq!(ret.len(), 1);
+        assert_eq!(ret[0].line.number, 3);
+    }
+
+    #[test]
+    fn test_extract_urls() {
+        let text = "[a](https://example.com \"title\") <https://rust-lang.org> [b](<c.md>)";
+        let ret: Vec<_> = extract_urls(text)
+            .into_iter()
+            .map(|(s, e)| &text[s..e])
+            .collect();
+        assert_eq!(
+            ret,
+            vec!["https://example.com", "https://rust-lang.org", "c.md"]
+        );
+
+        let text = "[ref]: https://example.com/ref";
+        let ret: Vec<_> = extract_urls(text)
+            .into_iter()
+            .map(|(s, e)| &text[s..e])
+            .collect();
+        assert_eq!(ret, vec!["https://example.com/ref"]);
+    }
+
+    #[test]
+    fn test_check_heading() {
+        let linter = linter(Vec::new());
+        let pair = TranslationPair {
+            original: vec![numbered_line(2, "## Apple {#apple}")],
+            translated: vec![numbered_line(4, "### りんご")],
+        };
+        let ret = linter.check_heading(&pair, Path::new("a.md"));
+        assert_eq!(ret.len(), 1);
+        assert_eq!(ret[0].line.number, 4);
+        assert!(matches!(
+            ret[0].kind,
+            LintErrorKind::HeadingLevelMismatch {
+                original: Some(2),
+                translated: Some(3)
+            }
+        ));
+
+        let ret = linter.check_preserved(
+            &pair,
+            Path::new("a.md"),
+            extract_heading_anchors,
+            str::to_owned,
+            |text| LintErrorKind::HeadingAnchorNotPreserved { text },
+        );
+        assert_eq!(ret.len(), 1);
+        assert_eq!(&ret[0].line.content[ret[0].start..ret[0].end], "{#apple}");
+
+        let pair = TranslationPair {
+            original: vec![numbered_line(2, "Apple"), numbered_line(3, "=====")],
+            translated: vec![numbered_line(5, "# りんご")],
+        };
+        let ret = linter.check_heading(&pair, Path::new("a.md"));
+        assert!(ret.is_empty());
+    }
+
+    #[test]
+    fn test_get_anchors() {
+        let content = r##"
+# Hello, `World`!
+
+<!--
+## Original
+-->
+## Hello World
+## Custom {#custom-id}
+
+```rust
+# fn main() {}
+```
+<a id="html-anchor"></a>
+"##;
+        let ret = get_anchors(content);
+        assert!(ret.contains("hello-world"));
+        assert!(ret.contains("hello-world-1"));
+        assert!(ret.contains("custom-id"));
+        assert!(ret.contains("html-anchor"));
+        assert!(!ret.contains("original"));
+        assert!(!ret.contains("fn-main"));
+    }
+
+    #[test]
+    fn test_check_link_target() {
+        let linter = linter(Vec::new());
+        let dir = TempDir::new();
+        dir.write("ch01.md", "# はじめに {#intro}\n## 使い方\n");
+        let path = dir.write(
+            "ch02.md",
+            "[a](ch01.md#intro) [b](ch01.md#使い方) [c](ch01.md#usage) [d](ch03.md) `[e](ch04.md)`\n\
+             [f](ch01.md#%E4%BD%BF%E3%81%84%E6%96%B9) [g](ch01.md#%E6%A6%82%E8%A6%81)\n",
+        );
+
+        let ret = linter
+            .check_link_target(&path, &mut HashMap::new())
+            .unwrap();
+        assert_eq!(ret.len(), 3);
+        assert!(
+            matches!(&ret[0].kind, LintErrorKind::LinkAnchorNotFound { text } if text == "ch01.md#usage")
+        );
+        assert!(
+            matches!(&ret[1].kind, LintErrorKind::LinkTargetNotFound { text } if text == "ch03.md")
+        );
+        assert!(
+            matches!(&ret[2].kind, LintErrorKind::LinkAnchorNotFound { text } if text == "ch01.md#%E6%A6%82%E8%A6%81")
+        );
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("%E6%A6%82%E8%A6%81"), "概要");
+        assert_eq!(percent_decode("a%20b.md"), "a b.md");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%+4%zz%4"), "%+4%zz%4");
+    }
+
+    #[test]
+    fn test_check_code_block() {
+        let mut linter = linter(Vec::new());
+        linter.enable_code_comment_tweak = true;
+        linter.code_comment_header = String::from("# ");
+
+        let dir = TempDir::new();
+        let source = dir.write(
+            "src.md",
+            "```rust\n// Hello\nlet x = 1; // one\n```\n\n```console\n$ cargo run\n```\n",
+        );
+        let target = dir.write(
+            "tgt.md",
+            "<!--\n```rust\n-->\n```rust\n# // Hello\n// こんにちは\nlet x = 1; // 一\n```\n\n```text\n$ cargo build\n```\n",
+        );
+
+        let ret = linter.check_code_block(&source, &target).unwrap();
+        assert_eq!(ret.len(), 2);
+        assert!(
+            matches!(&ret[0].kind, LintErrorKind::CodeBlockInfoMismatch { original, translated } if original == "console" && translated == "text")
+        );
+        assert!(
+            matches!(&ret[1].kind, LintErrorKind::CodeBlockMismatch { original } if original == "$ cargo run")
+        );
+        assert_eq!(ret[1].line.number, 11);
+    }
+
+    #[test]
+    fn test_check_code_block_missing() {
+        let mut linter = linter(Vec::new());
+        linter.enable_code_block_check = true;
+
+        let dir = TempDir::new();
+        let source = dir.write("src.md", "```rust\nx\n```\n\n```rust\ny\n```\n");
+        let text = "```rust\nx\n```\n\nテキスト\n";
+        let target = dir.write("tgt.md", text);
+
+        let ret = linter.check_code_block(&source, &target).unwrap();
+        assert_eq!(ret.len(), 1);
+        assert!(
+            matches!(&ret[0].kind, LintErrorKind::CodeBlockMissing { original } if original.ends_with("src.md:5"))
+        );
+        assert_eq!(ret[0].path, target);
+        assert_eq!(ret[0].line.number, 3);
+
+        // Missing code block can be suppressed in the target
+        let text = format!("<!-- transcheck-disable code-block -->\n{}", text);
+        let target = dir.write("tgt.md", &text);
+        let target_only = TargetOnly {
+            source_path: source,
+            target_path: target,
+            lines: Vec::new(),
+            pairs: Vec::new(),
+            directives: Directives::parse(&text),
+        };
+        assert!(linter.check(vec![target_only]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_structure() {
+        let linter = linter(Vec::new());
+        let pair = TranslationPair {
+            original: vec![
+                numbered_line(2, "* Apple"),
+                numbered_line(3, "  * Orange"),
+                numbered_line(4, "* Banana"),
+                numbered_line(5, ""),
+                numbered_line(6, "| Name | Value |"),
+                numbered_line(7, "|:-----|------:|"),
+                numbered_line(8, "> Note[^1]"),
+            ],
+            translated: vec![
+                numbered_line(10, "* りんご"),
+                numbered_line(11, "* オレンジ"),
+                numbered_line(12, "* バナナ"),
+                numbered_line(13, ""),
+                numbered_line(14, "| 名前 | 値 |"),
+                numbered_line(15, "|:-----|------:|"),
+                numbered_line(16, "> 注意"),
+            ],
+        };
+        let ret = linter.check_structure(&pair, Path::new("a.md"));
+        assert_eq!(ret.len(), 1);
+        assert!(
+            matches!(&ret[0].kind, LintErrorKind::ListMismatch { original, translated } if original == &vec![0, 1, 0] && translated == &vec![0, 0, 0])
+        );
+        assert_eq!(ret[0].line.number, 10);
+
+        // Thematic breaks are not list items
+        let breaks = TranslationPair {
+            original: vec![
+                numbered_line(2, "* Apple"),
+                numbered_line(3, ""),
+                numbered_line(4, "* * *"),
+            ],
+            translated: vec![
+                numbered_line(6, "* りんご"),
+                numbered_line(7, ""),
+                numbered_line(8, "---"),
+            ],
+        };
+        assert!(linter
+            .check_structure(&breaks, Path::new("a.md"))
+            .is_empty());
+        let breaks = TranslationPair {
+            original: vec![numbered_line(2, "* Apple")],
+            translated: vec![numbered_line(4, "* りんご"), numbered_line(5, "- - -")],
+        };
+        assert!(linter
+            .check_structure(&breaks, Path::new("a.md"))
+            .is_empty());
+
+        let ret = linter.check_preserved(
+            &pair,
+            Path::new("a.md"),
+            extract_footnotes,
+            str::to_owned,
+            |text| LintErrorKind::FootnoteNotPreserved { text },
+        );
+        assert_eq!(ret.len(), 1);
+    }
+
+    #[test]
+    fn test_check_untranslated() {
+        let mut linter = linter(Vec::new());
+        linter.untranslated_allowlist = vec![String::from("Visual Studio Code")];
+        linter.untranslated_threshold = 0.5;
+
+        let dir = TempDir::new();
+        let path = dir.write(
+            "a.md",
+            r#"---
+title: The title of this chapter
+---
+<!--
+This is the original.
+-->
+これは翻訳です。`cargo build --release` を実行します。
+This line is left untranslated.
+Visual Studio Code を使います。
+
+```rust
+let x = "not a translation target";
+```
+
+<div class="warning">
+This is a raw HTML block.
+</div>
+"#,
+        );
+
+        let ret = linter
+            .check_untranslated(TargetScript::Japanese, &path)
+            .unwrap();
+        assert_eq!(ret.len(), 1);
+        assert_eq!(ret[0].line.number, 8);
+    }
+
+    #[test]
+    fn test_check_cjk_typography() {
+        let linter = linter(Vec::new());
+        let path = Path::new("a.md");
+
+        let ret = linter.check_cjk_space(CjkSpacing::Required, &line("Rustは2015年に"), path);
+        assert_eq!(ret.len(), 3);
+        assert_eq!(&ret[0].line.content[ret[0].start..ret[0].end], "tは");
+        let ret = linter.check_cjk_space(CjkSpacing::Forbidden, &line("Rust は 2015 年に"), path);
+        assert_eq!(ret.len(), 3);
+        let ret = linter.check_cjk_space(CjkSpacing::Forbidden, &line("Rustは2015年に"), path);
+        assert!(ret.is_empty());
+
+        let ret = linter.check_punctuation_width(&line("これは,ペンです. v1.0 です。"), path);
+        assert_eq!(ret.len(), 2);
+
+        let ret = linter.check_quote_style(QuoteStyle::Corner, &line("「A」と“B”"), path);
+        assert_eq!(ret.len(), 1);
+        assert_eq!(&ret[0].line.content[ret[0].start..ret[0].end], "“B”");
+
+        let ret = linter.check_full_width_alnum(&line("ＲＵＳＴ 2015"), path);
+        assert_eq!(ret.len(), 1);
+
+        // Code spans and URLs are not checked
+        let text =
+            "`let s=\"日本,x“Ａ”\";`を[使う](https://example.com/日本,x“Ａ”)<https://例.jp/日本,x>";
+        assert!(linter
+            .check_cjk_space(CjkSpacing::Required, &line(text), path)
+            .is_empty());
+        assert!(linter.check_punctuation_width(&line(text), path).is_empty());
+        assert!(linter
+            .check_quote_style(QuoteStyle::Corner, &line(text), path)
+            .is_empty());
+        assert!(linter.check_full_width_alnum(&line(text), path).is_empty());
+    }
+
+    #[test]
+    fn test_check_markdown() {
+        let linter = Linter {
+            enable_emphasis_check: true,
+            enable_half_paren_check: true,
+            enable_full_paren_check: true,
+            ..Default::default()
+        };
+
+        let dir = TempDir::new();
+        let text = r##"<!--
+This is **important** (note).
+-->
+これは**重要**です。
+これは**「重要」**です(注意)。
+`**a**(コード)` と [リンク](https://example.com/(あ)) は対象外です。
+* リスト項目 *
+**強調*
+
+これは__「重要」__です。snake_case と \*注意\* は対象外です。
+
+これは**「とても
+重要」**です。
+
+```rust
+let x = **y**(あ);
+```
+"##;
+        let path = dir.write("markdown.md", text);
+
+        let target_only = TargetOnly {
+            source_path: PathBuf::from("source.md"),
+            target_path: path.clone(),
+            lines: text
+                .lines()
+                .enumerate()
+                .skip(3)
+                .map(|(i, x)| numbered_line(i + 1, x))
+                .collect(),
+            pairs: Vec::new(),
+            directives: Default::default(),
+        };
+        let ret = linter.check_markdown(&target_only).unwrap();
+        let ret: Vec<_> = ret
+            .iter()
+            .map(|x| (x.line.number, &x.line.content[x.start..x.end], x.kind.id()))
+            .collect();
+        assert_eq!(
+            ret,
+            vec![
+                (5, "**「重要」**", "emphasis"),
+                (5, "(注意)", "half-paren"),
+                (8, "*強調*", "emphasis"),
+                (10, "__「重要」__", "emphasis"),
+                (12, "**「とても", "emphasis"),
+                (13, "重要」**", "emphasis"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_source() {
+        let linter = Linter {
+            enable_comment_check: true,
+            enable_trailing_whitespace_check: true,
+            ..Default::default()
+        };
+
+        let dir = TempDir::new();
+        let path = dir.write(
+            "source.md",
+            "# Hello \n<!-- ok -->\nText <!-- inline -->\n<!--\nok\n-->\n<!--\nnot closed\n",
+        );
+
+        let ret = linter.check_source(std::slice::from_ref(&path)).unwrap();
+        let ret: Vec<_> = ret
+            .iter()
+            .map(|x| (x.line.number, &x.line.content[x.start..x.end], x.kind.id()))
+            .collect();
+        assert_eq!(
+            ret,
+            vec![
+                (3, "<!-- inline -->", "comment"),
+                (7, "<!--", "comment"),
+                (1, " ", "trailing-whitespace"),
+            ]
+        );
+
+        // Stray `-->` is reported by the same parser as the matcher
+        let text = "<!-- a --> b\n-->\n<!-- c -->\n";
+        let lines: Vec<_> = text
+            .lines()
+            .enumerate()
+            .map(|(i, x)| numbered_line(i + 1, x))
+            .collect();
+        let ret = linter.check_comment(text, &lines, Path::new("source.md"));
+        assert_eq!(ret.len(), 2);
+        assert!(matches!(ret[0].kind, LintErrorKind::InlineComment));
+        assert_eq!(&ret[0].line.content[ret[0].start..ret[0].end], "<!-- a -->");
+        assert!(matches!(ret[1].kind, LintErrorKind::MisorderedComment));
+        assert_eq!((ret[1].line.number, ret[1].start, ret[1].end), (2, 0, 3));
+    }
+
+    #[test]
+    fn test_get_code_blocks() {
+        let content = r##"
+<!--
+~~~rust
+-->
+~~~rust
+let x = 1;
+~~~
+
+````markdown
+```
+````
+
+    $ cargo run
+
+    $ cargo test
+text
+"##;
+        let ret = get_code_blocks(content);
+        assert_eq!(ret.len(), 3);
+        assert_eq!(ret[0].info, "rust");
+        assert_eq!(ret[0].fence.number, 5);
+        assert_eq!(ret[0].lines.len(), 1);
+        assert_eq!(ret[1].info, "markdown");
+        assert_eq!(ret[1].lines[0].content, "```");
+        assert_eq!(ret[2].info, "");
+        assert_eq!(ret[2].lines.len(), 2);
+        assert_eq!(ret[2].end.number, 15);
+    }
 }
@@ -1,15 +1,24 @@
+mod comment;
 mod config;
+mod directive;
+mod fence;
 mod fixer;
+mod glossary;
+mod journal;
 mod linter;
 mod matcher;
 mod printer;
+mod syntax;
 mod util;
 
 use crate::config::Config;
 use crate::fixer::Fixer;
-use crate::linter::Linter;
-use crate::matcher::Matcher;
-use crate::printer::Printer;
+use crate::glossary::Glossary;
+use crate::journal::{Journal, JournalRun};
+use crate::linter::{Linter, Rule};
+use crate::matcher::{Matcher, Mismatch, TargetOnly};
+use crate::printer::{Printer, Summary};
+use crate::syntax::CodeCommentSyntax;
 use crate::util::print_error;
 use anyhow::{Context, Error};
 use std::env;
@@ -29,17 +38,25 @@ use structopt::{clap, StructOpt};
 #[structopt(setting(clap::AppSettings::DeriveDisplayOrder))]
 pub struct Opt {
     /// Source directory ( or file if single file mode )
-    #[structopt(name = "SOURCE")]
-    pub source: PathBuf,
+    #[structopt(name = "SOURCE", required_unless = "undo")]
+    pub source: Option<PathBuf>,
 
     /// Target directory ( or file if single file mode )
-    #[structopt(name = "TARGET")]
-    pub target: PathBuf,
+    #[structopt(name = "TARGET", required_unless = "undo")]
+    pub target: Option<PathBuf>,
+
+    /// Synchronization check ( default if neither --fix nor --lint is specified )
+    #[structopt(long = "check")]
+    pub check: bool,
 
     /// Apply auto fix
     #[structopt(long = "fix")]
     pub fix: bool,
 
+    /// Revert the last auto fix
+    #[structopt(long = "undo", conflicts_with_all = &["check", "fix", "lint"])]
+    pub undo: bool,
+
     /// Lint check
     #[structopt(long = "lint")]
     pub lint: bool,
@@ -60,6 +77,10 @@ pub struct Opt {
     #[structopt(long = "config", default_value = "transcheck.toml")]
     pub config: PathBuf,
 
+    /// Journal file to record auto fix
+    #[structopt(long = "journal", default_value = ".transcheck-journal.toml")]
+    pub journal: PathBuf,
+
     /// Color mode
     #[structopt(
         short = "c",
@@ -72,28 +93,46 @@ pub struct Opt {
     pub color: String,
 }
 
+// ---------------------------------------------------------------------------------------------------------------------
+// Exit status
+// ---------------------------------------------------------------------------------------------------------------------
+
+/// Target is not synchronized with source
+const EXIT_MISMATCH: i32 = 1;
+/// Target has lint errors
+const EXIT_LINT_ERROR: i32 = 2;
+/// Auto fix applied changes to target
+const EXIT_FIXED: i32 = 4;
+/// Tool error like invalid option, unparsable config or IO failure
+const EXIT_TOOL_ERROR: i32 = 8;
+
 // ---------------------------------------------------------------------------------------------------------------------
 // Main
 // ---------------------------------------------------------------------------------------------------------------------
 
 #[cfg_attr(tarpaulin, skip)]
 fn main() {
-    match run() {
+    let opt = match Opt::from_args_safe() {
+        Ok(x) => x,
+        Err(x) if x.use_stderr() => {
+            eprintln!("{}", x.message);
+            process::exit(EXIT_TOOL_ERROR);
+        }
+        Err(x) => x.exit(),
+    };
+
+    match run(opt) {
         Ok(x) => {
-            if !x {
-                process::exit(1);
-            }
+            process::exit(x);
         }
         Err(x) => {
             print_error(x);
-            process::exit(1);
+            process::exit(EXIT_TOOL_ERROR);
         }
     }
 }
 
-fn run() -> Result<bool, Error> {
-    let opt = Opt::from_args();
-
+fn run(opt: Opt) -> Result<i32, Error> {
     match opt.color.as_str() {
         "auto" => console::set_colors_enabled(console::Term::stdout().is_term()),
         "always" => console::set_colors_enabled(true),
@@ -101,14 +140,23 @@ fn run() -> Result<bool, Error> {
         _ => unreachable!(),
     }
 
-    let config = search_config(&opt.config);
+    if opt.undo {
+        return undo(&opt);
+    }
+
+    let source = opt.source.as_ref().unwrap();
+    let target = opt.target.as_ref().unwrap();
+
+    let config_path = search_config(&opt.config);
 
-    let config = if let Some(config) = config {
-        let mut f = File::open(&config)
+    let config: Config = if let Some(ref config) = config_path {
+        let mut f = File::open(config)
             .with_context(|| format!("Failed to open '{}'", config.to_string_lossy()))?;
         let mut s = String::new();
         let _ = f.read_to_string(&mut s);
-        let ret = toml::from_str(&s)
+        let ret: Config = toml::from_str(&s)
+            .with_context(|| format!("Failed to parse toml '{}'", config.to_string_lossy()))?;
+        ret.validate()
             .with_context(|| format!("Failed to parse toml '{}'", config.to_string_lossy()))?;
 
         ret
@@ -116,46 +164,196 @@ fn run() -> Result<bool, Error> {
         Config::default()
     };
 
+    let code_comment_syntax = CodeCommentSyntax::new(&config.matcher.code_comment_syntax);
+
     let matcher = Matcher {
         enable_code_comment_tweak: config.matcher.enable_code_comment_tweak,
-        code_comment_header: config.matcher.code_comment_header,
+        code_comment_header: config.matcher.code_comment_header.clone(),
+        code_comment_syntax: code_comment_syntax.clone(),
         keep_markdown_comment: config.matcher.keep_markdown_comment,
         markdown_comment_begin: config.matcher.markdown_comment_begin,
         markdown_comment_end: config.matcher.markdown_comment_end,
         similar_threshold: config.matcher.similar_threshold,
     };
 
+    // Glossary path is relative to the configuration file
+    let glossary = if let Some(ref glossary) = config.linter.glossary {
+        let base = config_path
+            .as_ref()
+            .and_then(|x| x.parent())
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        Some(Glossary::load(&base.join(glossary))?)
+    } else {
+        None
+    };
+
     let linter = Linter {
         enable_emphasis_check: config.linter.enable_emphasis_check,
         enable_half_paren_check: config.linter.enable_half_paren_check,
         enable_full_paren_check: config.linter.enable_full_paren_check,
+        enable_code_span_check: config.linter.enable_code_span_check,
+        enable_url_check: config.linter.enable_url_check,
+        enable_heading_check: config.linter.enable_heading_check,
+        enable_heading_anchor_check: config.linter.enable_heading_anchor_check,
+        enable_link_target_check: config.linter.enable_link_target_check,
+        enable_placeholder_check: config.linter.enable_placeholder_check,
+        enable_number_check: config.linter.enable_number_check,
+        enable_code_block_check: config.linter.enable_code_block_check,
+        enable_structure_check: config.linter.enable_structure_check,
+        cjk_latin_space: config.linter.cjk_latin_space,
+        enable_punctuation_width_check: config.linter.enable_punctuation_width_check,
+        quote_style: config.linter.quote_style,
+        enable_full_width_alnum_check: config.linter.enable_full_width_alnum_check,
+        enable_comment_check: false,
+        enable_trailing_whitespace_check: false,
+        enable_code_comment_tweak: config.matcher.enable_code_comment_tweak,
+        code_comment_header: config.matcher.code_comment_header.clone(),
+        code_comment_syntax: code_comment_syntax.clone(),
+        rules: config
+            .linter
+            .rules
+            .iter()
+            .map(Rule::new)
+            .collect::<Result<_, _>>()?,
+        glossary,
+        target_script: config.linter.target_script,
+        untranslated_allowlist: config.linter.untranslated_allowlist.clone(),
+        untranslated_threshold: config.linter.untranslated_threshold,
+        severity: config.severity.clone(),
+    };
+
+    let source_linter = Linter {
+        enable_comment_check: config.source_linter.enable_comment_check,
+        enable_trailing_whitespace_check: config.source_linter.enable_trailing_whitespace_check,
+        rules: config
+            .source_linter
+            .rules
+            .iter()
+            .map(Rule::new)
+            .collect::<Result<_, _>>()?,
+        severity: config.severity.clone(),
+        ..Default::default()
     };
 
     let printer = Printer {
         verbose: opt.verbose,
+        severity: config.severity.clone(),
     };
 
-    let excludes: Vec<_> = config.excludes.iter().map(|x| x.into()).collect();
-    let (mismatches, target_onlys) = if opt.single {
-        let (mismatch, target_only) = matcher.check_file(&opt.source, &opt.target)?;
-        (vec![mismatch], vec![target_only])
-    } else {
-        matcher.check_dir(&opt.source, &opt.target, &excludes)?
+    let excludes: Vec<_> = config.excludes.iter().collect();
+    let walk = || -> Result<(Vec<Mismatch>, Vec<TargetOnly>), Error> {
+        if opt.single {
+            let (mismatch, target_only) = matcher.check_file(source, target)?;
+            Ok((vec![mismatch], target_only.into_iter().collect()))
+        } else {
+            matcher.check_dir(source, target, &excludes)
+        }
     };
+    let (mut mismatches, mut target_onlys) = walk()?;
+
+    let check = opt.check || !(opt.fix || opt.lint);
+    let mut status = 0;
+    let mut summary = Summary::default();
 
-    let success = if opt.fix {
+    if opt.fix {
         let fixer = Fixer {
             dry_run: opt.dry_run,
+            enable_code_comment_tweak: config.matcher.enable_code_comment_tweak,
+            code_comment_header: config.matcher.code_comment_header.clone(),
+            code_comment_syntax,
         };
-        fixer.fix(&mismatches)?
-    } else if opt.lint {
-        let lint_errors = linter.check(target_onlys)?;
-        printer.print_lint(&lint_errors)?
+        let mut changes = fixer.fix(&mismatches)?;
+        if opt.lint {
+            // Lint errors are fixed on the target fixed above
+            if !opt.dry_run && !changes.is_empty() {
+                (mismatches, target_onlys) = walk()?;
+            }
+            let lint_errors = linter.check(target_onlys.clone())?;
+            changes.append(&mut fixer.fix_lint(&lint_errors)?);
+        }
+        summary.fixes = Some(changes.len());
+        if opt.dry_run && !changes.is_empty() {
+            status |= EXIT_MISMATCH;
+        } else if !changes.is_empty() {
+            for change in &mut changes {
+                change.path = std::path::absolute(&change.path).with_context(|| {
+                    format!("Failed to resolve '{}'", change.path.to_string_lossy())
+                })?;
+            }
+            let mut journal = Journal::load(&opt.journal)?;
+            journal.runs.push(JournalRun { changes });
+            journal.save(&opt.journal)?;
+            status |= EXIT_FIXED;
+
+            // The following checks should see the fixed target
+            if check || opt.lint {
+                (mismatches, target_onlys) = walk()?;
+            }
+        }
+    }
+
+    if check {
+        let errors = printer.print_mismatch(&mismatches)?;
+        summary.mismatches = Some(errors);
+        if errors != 0 {
+            status |= EXIT_MISMATCH;
+        }
     } else {
-        printer.print_mismatch(&mismatches)?
+        // Malformed comments are not fixed nor linted, so they are reported in every mode
+        let errors = printer.print_malformed_comments(&mismatches)?;
+        if errors != 0 {
+            summary.mismatches = Some(errors);
+            status |= EXIT_MISMATCH;
+        }
+    }
+
+    if opt.lint {
+        let lint_errors = linter.check(target_onlys)?;
+        let mut errors = printer.print_lint(&lint_errors)?;
+
+        let source_lint_errors = if opt.single {
+            source_linter.check_source(&[PathBuf::from(source)])?
+        } else {
+            source_linter.check_source(&Matcher::source_files(source, &excludes)?)?
+        };
+        errors += printer.print_source_lint(&source_lint_errors)?;
+
+        summary.lint_errors = Some(errors);
+        if errors != 0 {
+            status |= EXIT_LINT_ERROR;
+        }
+    }
+
+    printer.print_summary(&summary);
+
+    Ok(status)
+}
+
+fn undo(opt: &Opt) -> Result<i32, Error> {
+    let mut journal = Journal::load(&opt.journal)?;
+    let run = journal
+        .runs
+        .pop()
+        .with_context(|| format!("No fix is recorded in '{}'", opt.journal.to_string_lossy()))?;
+
+    let fixer = Fixer {
+        dry_run: opt.dry_run,
+        enable_code_comment_tweak: false,
+        code_comment_header: String::new(),
+        code_comment_syntax: CodeCommentSyntax::default(),
     };
+    fixer.undo(&run).with_context(|| {
+        format!(
+            "Failed to undo the fix recorded in '{}'",
+            opt.journal.to_string_lossy()
+        )
+    })?;
 
-    Ok(success)
+    if !opt.dry_run {
+        journal.save(&opt.journal)?;
+    }
+    Ok(0)
 }
 
 #[cfg_attr(tarpaulin, skip)]
@@ -172,3 +370,124 @@ fn search_config(path: &Path) -> Option<PathBuf> {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::TempDir;
+
+    /// Create the source and the target in a temporary directory
+    fn book(source: &str, target: &str) -> TempDir {
+        let dir = TempDir::new();
+        dir.write("src/01.md", source);
+        dir.write("tgt/01.md", target);
+        dir
+    }
+
+    /// Run with `args` on the book in `dir` and return the exit status
+    fn run_with(dir: &TempDir, args: &[&str]) -> Result<i32, Error> {
+        let path = |x| dir.path().join(x).to_string_lossy().into_owned();
+        let mut opt = vec![
+            String::from("mdbook-transcheck"),
+            String::from("--color=disable"),
+            format!("--journal={}", path("journal.toml")),
+            format!("--config={}", path("transcheck.toml")),
+        ];
+        opt.extend(args.iter().map(|x| x.to_string()));
+        if !args.contains(&"--undo") {
+            opt.push(path("src"));
+            opt.push(path("tgt"));
+        }
+        run(Opt::from_iter_safe(opt)?)
+    }
+
+    #[test]
+    fn test_run_malformed_comment() {
+        for args in [&[][..], &["--fix"], &["--lint"], &["--fix", "--lint"]] {
+            let dir = book("aaa\n", "<!--\naaa\n-->\nあああ\n-->\n");
+            assert_eq!(run_with(&dir, args).unwrap(), EXIT_MISMATCH, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_run_exit_status() {
+        let dir = book("aaa\n", "<!--\naaa\n-->\nあああ\n");
+        assert_eq!(run_with(&dir, &[]).unwrap(), 0);
+        assert_eq!(run_with(&dir, &["--lint"]).unwrap(), 0);
+
+        // Mismatch is reported by check, fixed by fix and reverted by undo
+        let dir = book(
+            "aaa\n\nbbb\n\nccc\n",
+            "<!--\naaa\n-->\nあああ\n\n<!--\nccc\n-->\nいいい\n",
+        );
+        assert_eq!(run_with(&dir, &[]).unwrap(), EXIT_MISMATCH);
+        assert_eq!(
+            run_with(&dir, &["--fix", "--dry-run"]).unwrap(),
+            EXIT_MISMATCH
+        );
+        assert_eq!(run_with(&dir, &["--fix"]).unwrap(), EXIT_FIXED);
+        assert_eq!(run_with(&dir, &[]).unwrap(), 0);
+        assert_eq!(run_with(&dir, &["--fix"]).unwrap(), 0);
+        assert_eq!(run_with(&dir, &["--undo"]).unwrap(), 0);
+        assert_eq!(run_with(&dir, &[]).unwrap(), EXIT_MISMATCH);
+
+        let dir = book("aaa\n", "<!--\naaa\n-->\nこれは**「強調」**です\n");
+        assert_eq!(run_with(&dir, &["--lint"]).unwrap(), EXIT_LINT_ERROR);
+
+        // Tool errors are returned as `Err` and mapped to `EXIT_TOOL_ERROR` by `main`
+        let dir = book("aaa\n", "<!--\naaa\n-->\nあああ\n");
+        dir.write("transcheck.toml", "[linter]\nunknown = true\n");
+        assert!(run_with(&dir, &[]).is_err());
+        let dir = book("aaa\n", "<!--\naaa\n-->\nあああ\n");
+        assert!(run_with(&dir, &["--undo"]).is_err());
+    }
+
+    #[test]
+    fn test_run_undo_copy() {
+        let dir = book("aaa\n", "<!--\naaa\n-->\nあああ\n");
+        dir.write("src/02.md", "bbb\n");
+        assert_eq!(run_with(&dir, &["--fix"]).unwrap(), EXIT_FIXED);
+        assert!(dir.path().join("tgt/02.md").exists());
+        assert_eq!(run_with(&dir, &["--undo"]).unwrap(), 0);
+        assert!(!dir.path().join("tgt/02.md").exists());
+
+        // The copied file translated after the fix is not deleted
+        assert_eq!(run_with(&dir, &["--fix"]).unwrap(), EXIT_FIXED);
+        let target = dir.write("tgt/02.md", "<!--\nbbb\n-->\nいいい\n");
+        assert!(run_with(&dir, &["--undo"]).is_err());
+        assert_eq!(
+            std::fs::read_to_string(target).unwrap(),
+            "<!--\nbbb\n-->\nいいい\n"
+        );
+    }
+
+    #[test]
+    fn test_run_combined_exit_status() {
+        let source = "aaa\n\nbbb\n\nccc\n";
+        let target = "<!--\naaa\n-->\nこれは**「強調」**です\n\n<!--\nccc\n-->\nいいい\n";
+
+        let dir = book(source, target);
+        assert_eq!(
+            run_with(&dir, &["--check", "--lint"]).unwrap(),
+            EXIT_MISMATCH | EXIT_LINT_ERROR
+        );
+
+        // Dry run reports changes as mismatch and the following checks see the original target
+        assert_eq!(
+            run_with(&dir, &["--check", "--fix", "--lint", "--dry-run"]).unwrap(),
+            EXIT_MISMATCH | EXIT_LINT_ERROR
+        );
+
+        // The following checks see the fixed target
+        assert_eq!(run_with(&dir, &["--check", "--fix"]).unwrap(), EXIT_FIXED);
+        assert_eq!(run_with(&dir, &["--lint"]).unwrap(), EXIT_LINT_ERROR);
+
+        // Lint errors are fixed after the mismatches
+        let dir = book(source, target);
+        assert_eq!(
+            run_with(&dir, &["--check", "--fix", "--lint"]).unwrap(),
+            EXIT_FIXED
+        );
+        assert_eq!(run_with(&dir, &["--check", "--lint"]).unwrap(), 0);
+    }
+}
@@ -1,10 +1,17 @@
+use crate::comment::{self, CommentError};
+use crate::directive::Directives;
+use crate::fence::{self, CodeLine, Fences};
+use crate::syntax::CodeCommentSyntax;
 use crate::util::print_warning;
-use anyhow::{bail, Context, Error};
+use anyhow::{Context, Error};
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 use walkdir::WalkDir;
 
 #[derive(Clone, Debug)]
@@ -14,6 +21,19 @@ pub struct Line {
     pub last_both: usize,
     pub html_comment: bool,
     pub code_not_comment: bool,
+    pub context: LineContext,
+}
+
+/// Markdown context of a line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LineContext {
+    #[default]
+    Text,
+    CodeBlock,
+    HtmlComment,
+    FrontMatter,
+    HtmlBlock,
 }
 
 #[derive(Clone, Debug)]
@@ -39,6 +59,26 @@ pub enum MismatchLine {
     Garbage(GarbageLine),
 }
 
+impl MismatchLine {
+    /// Rule id used by suppression directives
+    pub fn id(&self) -> &'static str {
+        match self {
+            MismatchLine::Modified(_) => "modified",
+            MismatchLine::Missing(_) => "missing",
+            MismatchLine::Garbage(_) => "garbage",
+        }
+    }
+
+    /// Line number in the target file
+    pub fn target_line(&self) -> usize {
+        match self {
+            MismatchLine::Modified(x) => x.target.number,
+            MismatchLine::Missing(x) => x.source.last_both + 1,
+            MismatchLine::Garbage(x) => x.target.number,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct MismatchLines {
     pub source_path: PathBuf,
@@ -52,22 +92,55 @@ pub struct MissingFile {
     pub target_path: PathBuf,
 }
 
+/// Markdown comment which can't be parsed in the source or the target
+#[derive(Clone, Debug)]
+pub struct MalformedComment {
+    pub source_path: PathBuf,
+    pub target_path: PathBuf,
+    pub in_target: bool,
+    pub error: CommentError,
+}
+
+impl MalformedComment {
+    pub fn path(&self) -> &Path {
+        if self.in_target {
+            &self.target_path
+        } else {
+            &self.source_path
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Mismatch {
     MissingFile(MissingFile),
     MismatchLines(MismatchLines),
+    MalformedComment(MalformedComment),
+}
+
+/// Original lines in a markdown comment and the translated lines following it
+#[derive(Clone, Debug)]
+pub struct TranslationPair {
+    pub original: Vec<Line>,
+    pub translated: Vec<Line>,
 }
 
 #[derive(Clone, Debug)]
 pub struct TargetOnly {
+    pub source_path: PathBuf,
     pub target_path: PathBuf,
     pub lines: Vec<Line>,
+    pub pairs: Vec<TranslationPair>,
+    pub directives: Directives,
 }
 
+type LinePair = (Vec<Line>, Vec<Line>);
+
 #[derive(Clone, Debug)]
 pub struct Matcher {
     pub enable_code_comment_tweak: bool,
     pub code_comment_header: String,
+    pub code_comment_syntax: CodeCommentSyntax,
     pub keep_markdown_comment: bool,
     pub markdown_comment_begin: String,
     pub markdown_comment_end: String,
@@ -84,7 +157,39 @@ impl Matcher {
         let mut mismatches = Vec::new();
         let mut target_onlys = Vec::new();
         let source = source.as_ref();
-        'warkdir: for entry in WalkDir::new(&source) {
+        for source_path in Matcher::source_files(source, excludes)? {
+            let mut target_path = PathBuf::new();
+            target_path.push(&target);
+            target_path.push(source_path.strip_prefix(source)?);
+            if !target_path.exists() {
+                let mismatch = Mismatch::MissingFile(MissingFile {
+                    source_path,
+                    target_path,
+                });
+                mismatches.push(mismatch);
+            } else {
+                let ret = self.check_file(&source_path, &target_path);
+                let (mismatch, target_only) = match ret {
+                    Ok(x) => x,
+                    Err(x) => {
+                        print_warning(x);
+                        continue;
+                    }
+                };
+                mismatches.push(mismatch);
+                target_onlys.extend(target_only);
+            }
+        }
+        Ok((mismatches, target_onlys))
+    }
+
+    /// Enumerate files in `source` except `excludes`
+    pub fn source_files<T: AsRef<Path>>(
+        source: &Path,
+        excludes: &[T],
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut ret = Vec::new();
+        'warkdir: for entry in WalkDir::new(source) {
             let source_path = entry
                 .with_context(|| format!("Failed to enumerate '{}'", source.to_string_lossy()))?
                 .into_path();
@@ -95,40 +200,18 @@ impl Matcher {
                 }
             }
 
-            let mut target_path = PathBuf::new();
-            target_path.push(&target);
-            target_path.push(source_path.strip_prefix(&source)?);
             if source_path.is_file() {
-                let source_path = PathBuf::from(&source_path);
-                let target_path = PathBuf::from(&target_path);
-                if !target_path.exists() {
-                    let mismatch = Mismatch::MissingFile(MissingFile {
-                        source_path,
-                        target_path,
-                    });
-                    mismatches.push(mismatch);
-                } else {
-                    let ret = self.check_file(&source_path, &target_path);
-                    let (mismatch, target_only) = match ret {
-                        Ok(x) => x,
-                        Err(x) => {
-                            print_warning(x);
-                            continue 'warkdir;
-                        }
-                    };
-                    mismatches.push(mismatch);
-                    target_onlys.push(target_only);
-                }
+                ret.push(source_path);
             }
         }
-        Ok((mismatches, target_onlys))
+        Ok(ret)
     }
 
     pub fn check_file<T: AsRef<Path>>(
         &self,
         source: T,
         target: T,
-    ) -> Result<(Mismatch, TargetOnly), Error> {
+    ) -> Result<(Mismatch, Option<TargetOnly>), Error> {
         let source_path = source.as_ref();
         let target_path = target.as_ref();
 
@@ -149,6 +232,22 @@ impl Matcher {
             .read_to_string(&mut target)
             .with_context(|| format!("Failed to read '{}'", target_path.to_string_lossy()))?;
 
+        for (text, in_target) in [(&source, false), (&target, true)] {
+            if let Err(error) = comment::scan(text) {
+                let mismatch = Mismatch::MalformedComment(MalformedComment {
+                    source_path: PathBuf::from(source_path),
+                    target_path: PathBuf::from(target_path),
+                    in_target,
+                    error,
+                });
+                return Ok((mismatch, None));
+            }
+        }
+
+        let pairs = Matcher::get_translation_pairs(&target);
+        let directives = Directives::parse(&target);
+        let contexts = Matcher::get_line_contexts(&target);
+
         let source = self.remove_markdown_comment(&source)?;
         let target = self.revert_code_comment(&target);
 
@@ -168,7 +267,10 @@ impl Matcher {
         let mut target = target.to_string();
         target.push_str(&"\n".repeat(additional_new_lines));
 
-        let (mismatch_lines, right_only_lines) = Matcher::get_mismatch_lines(&source, &target);
+        let (mismatch_lines, mut right_only_lines) = self.get_mismatch_lines(&source, &target);
+        for line in &mut right_only_lines {
+            line.context = contexts.get(line.number - 1).copied().unwrap_or_default();
+        }
 
         let mut lines = Vec::new();
         let mut last_modified_line = None;
@@ -177,9 +279,9 @@ impl Matcher {
             for left in &lefts {
                 let (similar_line, r, garbage) = self.get_similar_line(left, rights);
                 for g in garbage {
-                    if g.html_comment {
-                        lines.push(MismatchLine::Garbage(GarbageLine { target: g.clone() }));
-                    } else if g.code_not_comment {
+                    if (g.html_comment || g.code_not_comment)
+                        && !Directives::is_directive(&g.content)
+                    {
                         lines.push(MismatchLine::Garbage(GarbageLine { target: g.clone() }));
                     }
                 }
@@ -199,14 +301,14 @@ impl Matcher {
                 }
             }
             for g in rights {
-                if g.html_comment {
-                    lines.push(MismatchLine::Garbage(GarbageLine { target: g.clone() }));
-                } else if g.code_not_comment {
+                if (g.html_comment || g.code_not_comment) && !Directives::is_directive(&g.content) {
                     lines.push(MismatchLine::Garbage(GarbageLine { target: g.clone() }));
                 }
             }
         }
 
+        lines.retain(|x| !directives.is_disabled(x.target_line(), x.id()));
+
         let mismatch = Mismatch::MismatchLines(MismatchLines {
             source_path: PathBuf::from(source_path),
             target_path: PathBuf::from(target_path),
@@ -214,30 +316,31 @@ impl Matcher {
         });
 
         let target_only = TargetOnly {
+            source_path: PathBuf::from(source_path),
             target_path: PathBuf::from(target_path),
             lines: right_only_lines,
+            pairs,
+            directives,
         };
 
-        Ok((mismatch, target_only))
+        Ok((mismatch, Some(target_only)))
     }
 
     fn revert_code_comment<'a>(&self, target: &'a str) -> Cow<'a, str> {
         if self.enable_code_comment_tweak {
             let mut ret = String::new();
-            let mut code_block = false;
-            for line in target.lines() {
-                if line.trim().starts_with("```") && !code_block {
-                    code_block = true;
-                } else if line.trim().ends_with("```") && code_block {
-                    code_block = false;
-                }
-
-                let line = if code_block & line.starts_with(&self.code_comment_header) {
-                    &line[2..]
-                } else {
-                    line
+            let codes = fence::track(target);
+            let syntaxes = self.code_comment_syntax.lines(&codes);
+            for ((line, code), syntax) in target.lines().zip(&codes).zip(syntaxes) {
+                let (indent, code_line) = match code {
+                    CodeLine::Indented => fence::split_indent(line),
+                    _ => ("", line),
                 };
-                ret.push_str(&format!("{}\n", line));
+                let header = syntax.map(|x| x.header_or(&self.code_comment_header));
+                match header.and_then(|x| code_line.strip_prefix(x)) {
+                    Some(x) => ret.push_str(&format!("{}{}\n", indent, x)),
+                    None => ret.push_str(&format!("{}\n", line)),
+                }
             }
 
             ret.into()
@@ -247,50 +350,56 @@ impl Matcher {
     }
 
     fn remove_markdown_comment<'a>(&self, source: &'a str) -> Result<Cow<'a, str>, Error> {
-        if source.find("<!--").is_some() {
-            let mut ret = String::new();
-            let mut pos = 0;
-            while let Some(i) = source[pos..].find("<!--") {
-                ret.push_str(&source[pos..pos + i]);
-                if let Some(j) = source[pos..].find("-->") {
-                    if self.keep_markdown_comment {
-                        ret.push_str(&self.markdown_comment_begin);
-                        ret.push_str(&source[pos + i + 4..pos + j]);
-                        ret.push_str(&self.markdown_comment_end);
-                    }
-                    pos += j + 3;
-                } else {
-                    bail!("Failed to parse markdown comment");
-                }
+        let comments = comment::scan(source)?;
+        if comments.is_empty() {
+            return Ok(source.into());
+        }
+
+        let mut ret = String::new();
+        let mut pos = 0;
+        for x in comments {
+            ret.push_str(&source[pos..x.begin]);
+            if self.keep_markdown_comment {
+                ret.push_str(&self.markdown_comment_begin);
+                ret.push_str(&source[x.begin + 4..x.end - 3]);
+                ret.push_str(&self.markdown_comment_end);
             }
-            ret.push_str(&source[pos..]);
-            Ok(ret.into())
-        } else {
-            Ok(source.into())
+            pos = x.end;
         }
+        ret.push_str(&source[pos..]);
+        Ok(ret.into())
     }
 
-    fn get_mismatch_lines(source: &str, target: &str) -> (Vec<(Vec<Line>, Vec<Line>)>, Vec<Line>) {
+    fn get_mismatch_lines(&self, source: &str, target: &str) -> (Vec<LinePair>, Vec<Line>) {
+        // Commented original text in the target is compared with the source without the delimiters
+        let comments = comment::track(target);
+        let target_lines: Vec<_> = target.lines().collect();
+        let mut view = String::new();
+        for (x, tracked) in target_lines.iter().zip(&comments) {
+            match &tracked.original {
+                Some(r) if !Directives::is_directive(x) => view.push_str(&x[r.clone()]),
+                _ => view.push_str(x),
+            }
+            view.push('\n');
+        }
+        if !target.ends_with('\n') {
+            view.pop();
+        }
+        let target = view.as_str();
+        let codes = fence::track(target);
+        let code_not_comment = self.code_comment_syntax.code_not_comment(target, &codes);
+
         let mut source_line = 0;
         let mut target_line = 0;
         let mut last_both_source_line = 0;
         let mut last_both_target_line = 0;
-        let mut target_comment = false;
-        let mut target_code = false;
         let mut left_lines = Vec::new();
         let mut right_lines = Vec::new();
         let mut mismatch_lines = Vec::new();
         let mut right_only_lines = Vec::new();
-        for d in diff::lines(&source, &target) {
+        for d in diff::lines(source, target) {
             match d {
-                diff::Result::Both(x, _) => {
-                    let end_of_code = if x.trim().ends_with("```") && target_code {
-                        target_code = false;
-                        true
-                    } else {
-                        false
-                    };
-
+                diff::Result::Both(_, _) => {
                     source_line += 1;
                     target_line += 1;
                     last_both_source_line = source_line;
@@ -301,16 +410,12 @@ impl Matcher {
                         let right_lines: Vec<_> = right_lines
                             .iter()
                             .filter(|x| x.html_comment)
-                            .map(|x| x.clone())
+                            .cloned()
                             .collect();
                         mismatch_lines.push((left_lines.clone(), right_lines));
                     }
                     left_lines.clear();
                     right_lines.clear();
-
-                    if x.trim().starts_with("```") && !target_code && !end_of_code {
-                        target_code = true;
-                    }
                 }
                 diff::Result::Left(x) => {
                     source_line += 1;
@@ -320,37 +425,35 @@ impl Matcher {
                         last_both: last_both_target_line,
                         html_comment: false,
                         code_not_comment: false,
+                        context: LineContext::Text,
                     };
                     left_lines.push(line);
                 }
                 diff::Result::Right(x) => {
-                    if x.trim().starts_with("-->") {
-                        target_comment = false;
-                    }
-                    let end_of_code = if x.trim().ends_with("```") && target_code {
-                        target_code = false;
-                        true
-                    } else {
-                        false
-                    };
-
                     target_line += 1;
+                    let html_comment = comments.get(target_line - 1).is_some_and(|tracked| {
+                        tracked.original.is_some()
+                            && !Directives::is_directive(target_lines[target_line - 1])
+                    });
                     let line = Line {
                         number: target_line,
                         content: String::from(x),
                         last_both: last_both_source_line,
-                        html_comment: target_comment,
-                        code_not_comment: target_code && !x.contains("//"),
+                        html_comment,
+                        code_not_comment: code_not_comment
+                            .get(target_line - 1)
+                            .copied()
+                            .unwrap_or_default(),
+                        context: LineContext::Text,
                     };
-                    right_only_lines.push(line.clone());
+                    // Lines out of the diff keep the whole content including comments
+                    right_only_lines.push(Line {
+                        content: String::from(
+                            target_lines.get(target_line - 1).copied().unwrap_or(x),
+                        ),
+                        ..line.clone()
+                    });
                     right_lines.push(line);
-
-                    if x.trim().starts_with("<!--") {
-                        target_comment = true;
-                    }
-                    if x.trim().starts_with("```") && !target_code && !end_of_code {
-                        target_code = true;
-                    }
                 }
             }
         }
@@ -361,7 +464,7 @@ impl Matcher {
             let right_lines: Vec<_> = right_lines
                 .iter()
                 .filter(|x| x.html_comment)
-                .map(|x| x.clone())
+                .cloned()
                 .collect();
             mismatch_lines.push((left_lines.clone(), right_lines));
         }
@@ -369,9 +472,175 @@ impl Matcher {
         (mismatch_lines, right_only_lines)
     }
 
-    fn get_similar_line<'a, 'b>(
+    /// Get the markdown context of each line
+    pub fn get_line_contexts(target: &str) -> Vec<LineContext> {
+        let comments = comment::track(target);
+        let mut fences = Fences::default();
+
+        let mut ret = Vec::new();
+        let mut block = Block::Text;
+        let mut paragraph = false;
+        for (i, x) in target.lines().enumerate() {
+            let trimmed = x.trim();
+            let context = match block {
+                Block::Text => {
+                    if i == 0 && trimmed == "---" {
+                        block = Block::FrontMatter;
+                        LineContext::FrontMatter
+                    } else if comments[i].commented && !comments[i].translated {
+                        LineContext::HtmlComment
+                    } else {
+                        match fences.next(x) {
+                            CodeLine::Open(_) => {
+                                block = Block::Code;
+                                LineContext::CodeBlock
+                            }
+                            CodeLine::Indented => LineContext::CodeBlock,
+                            _ => match html_block_start(x, paragraph) {
+                                Some(end) => {
+                                    if !end.is_some_and(|end| x.contains(end)) {
+                                        block = Block::Html(end);
+                                    }
+                                    LineContext::HtmlBlock
+                                }
+                                None => LineContext::Text,
+                            },
+                        }
+                    }
+                }
+                Block::Code => {
+                    if fences.next(x) == CodeLine::Close {
+                        block = Block::Text;
+                    }
+                    LineContext::CodeBlock
+                }
+                Block::FrontMatter => {
+                    if trimmed == "---" || trimmed == "..." {
+                        block = Block::Text;
+                    }
+                    LineContext::FrontMatter
+                }
+                Block::Html(Some(end)) => {
+                    if x.contains(end) {
+                        block = Block::Text;
+                    }
+                    LineContext::HtmlBlock
+                }
+                Block::Html(None) if trimmed.is_empty() => {
+                    block = Block::Text;
+                    LineContext::Text
+                }
+                Block::Html(None) => LineContext::HtmlBlock,
+            };
+            paragraph =
+                context == LineContext::Text && !trimmed.is_empty() && !trimmed.starts_with('#');
+            ret.push(context);
+        }
+        ret
+    }
+
+    /// Pair each paragraph in markdown comments with the translated paragraph following it
+    ///
+    /// If the number of the translated paragraphs is less than the original,
+    /// the whole comment is paired with all the translated paragraphs.
+    fn get_translation_pairs(target: &str) -> Vec<TranslationPair> {
+        let mut blocks = Vec::new();
+        let mut original = Vec::new();
+        let mut translated = Vec::new();
+        let mut prev_original = false;
+        for (i, (x, tracked)) in target.lines().zip(comment::track(target)).enumerate() {
+            if Directives::is_directive(x) {
+                continue;
+            }
+            let line = |content: &str, html_comment| Line {
+                number: i + 1,
+                content: String::from(content),
+                last_both: 0,
+                html_comment,
+                code_not_comment: false,
+                context: LineContext::Text,
+            };
+
+            if let Some(r) = tracked.original.clone() {
+                // A comment after the translated lines begins the next block
+                if !prev_original || !translated.is_empty() {
+                    if !original.is_empty() {
+                        blocks.push((original.clone(), translated.clone()));
+                    }
+                    original.clear();
+                    translated.clear();
+                }
+                original.push(line(&x[r], true));
+            }
+            if tracked.translated || !tracked.commented {
+                translated.push(line(x, false));
+            }
+            prev_original = tracked.original.is_some();
+        }
+        if !original.is_empty() {
+            blocks.push((original, translated));
+        }
+
+        let mut pairs = Vec::new();
+        for (original, translated) in blocks {
+            let original = Matcher::split_paragraphs(&original);
+            let mut translated = Matcher::split_paragraphs(&translated);
+
+            // Code blocks following the comment are not translation of text paragraphs
+            let is_code = |x: &Vec<Line>| Fences::default().next(&x[0].content) != CodeLine::Text;
+            if !original.iter().any(is_code) {
+                translated.retain(|x| !is_code(x));
+            }
+
+            if translated.len() >= original.len() {
+                for (original, translated) in original.into_iter().zip(translated) {
+                    pairs.push(TranslationPair {
+                        original,
+                        translated,
+                    });
+                }
+            } else if !translated.is_empty() {
+                pairs.push(TranslationPair {
+                    original: original.into_iter().flatten().collect(),
+                    translated: translated.into_iter().flatten().collect(),
+                });
+            }
+        }
+        pairs
+    }
+
+    fn split_paragraphs(lines: &[Line]) -> Vec<Vec<Line>> {
+        let mut ret = Vec::new();
+        let mut paragraph = Vec::new();
+        let mut fences = Fences::default();
+        let mut prev_number = None;
+        for line in lines {
+            // Non-contiguous lines belong to different paragraphs
+            let contiguous = prev_number.map(|x| x + 1 == line.number).unwrap_or(true);
+            prev_number = Some(line.number);
+
+            let code_block = matches!(fences.next(&line.content), CodeLine::Fenced);
+            if (!code_block && line.content.trim().is_empty()) || !contiguous {
+                if !paragraph.is_empty() {
+                    ret.push(paragraph.clone());
+                }
+                paragraph.clear();
+                if line.content.trim().is_empty() {
+                    continue;
+                }
+            }
+
+            paragraph.push(line.clone());
+        }
+        if !paragraph.is_empty() {
+            ret.push(paragraph);
+        }
+        ret
+    }
+
+    fn get_similar_line<'b>(
         &self,
-        source: &'a Line,
+        source: &Line,
         target: &'b [Line],
     ) -> (Option<Line>, &'b [Line], &'b [Line]) {
         let mut max_similarity = 0.0;
@@ -380,13 +649,7 @@ impl Matcher {
         for (i, t) in target.iter().enumerate() {
             let common_chars = diff::chars(&source.content, &t.content)
                 .iter()
-                .filter(|x| {
-                    if let diff::Result::Both(_, _) = x {
-                        true
-                    } else {
-                        false
-                    }
-                })
+                .filter(|x| matches!(x, diff::Result::Both(_, _)))
                 .count();
             let source_similarity = common_chars as f64 / source.content.len() as f64;
             let target_similarity = common_chars as f64 / t.content.len() as f64;
@@ -409,9 +672,163 @@ impl Matcher {
     }
 }
 
+/// Block continuing over lines in `Matcher::get_line_contexts`
+#[derive(Clone, Copy, Debug)]
+enum Block {
+    Text,
+    Code,
+    FrontMatter,
+    /// HTML block closed by the line containing the end condition, or a blank line if `None`
+    Html(Option<&'static str>),
+}
+
+/// Tag names of HTML blocks which can interrupt a paragraph
+const HTML_BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "base",
+    "basefont",
+    "blockquote",
+    "body",
+    "caption",
+    "center",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "frame",
+    "frameset",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "iframe",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "menu",
+    "menuitem",
+    "nav",
+    "noframes",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "param",
+    "search",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "ul",
+];
+
+static HTML_OPEN_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^<[A-Za-z][A-Za-z0-9-]*(?:\s+[A-Za-z_:][A-Za-z0-9_.:-]*(?:\s*=\s*(?:[^\s"'=<>`]+|'[^']*'|"[^"]*"))?)*\s*/?>\s*$"#,
+    )
+    .unwrap()
+});
+static HTML_CLOSE_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^</[A-Za-z][A-Za-z0-9-]*\s*>\s*$").unwrap());
+
+/// Check whether `line` starts an HTML block by the start conditions of CommonMark
+///
+/// Returns the end condition of the block, `None` means a blank line.
+/// HTML comments are not handled here because they are tracked by `comment::track`.
+fn html_block_start(line: &str, paragraph: bool) -> Option<Option<&'static str>> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let line = &line[indent..];
+    if indent > 3 || !line.starts_with('<') {
+        return None;
+    }
+
+    let lower = line.to_ascii_lowercase();
+    let raw = [
+        ("<script", "</script>"),
+        ("<pre", "</pre>"),
+        ("<style", "</style>"),
+        ("<textarea", "</textarea>"),
+    ];
+    for (begin, end) in raw {
+        if let Some(rest) = lower.strip_prefix(begin) {
+            if rest.is_empty() || rest.starts_with(|c: char| c.is_ascii_whitespace() || c == '>') {
+                return Some(Some(end));
+            }
+        }
+    }
+    if lower.starts_with("<?") {
+        return Some(Some("?>"));
+    }
+    if lower.starts_with("<![cdata[") {
+        return Some(Some("]]>"));
+    }
+    if lower[1..].starts_with('!') && lower[2..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Some(Some(">"));
+    }
+
+    let rest = lower[1..].strip_prefix('/').unwrap_or(&lower[1..]);
+    let name_len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    let (name, rest) = rest.split_at(name_len);
+    let tag_end = rest.is_empty()
+        || rest.starts_with(|c: char| c.is_ascii_whitespace() || c == '>')
+        || rest.starts_with("/>");
+    if HTML_BLOCK_TAGS.contains(&name) && tag_end {
+        return Some(None);
+    }
+
+    // Other tags must occupy the whole line, and can't interrupt a paragraph
+    if !paragraph && (HTML_OPEN_TAG.is_match(line) || HTML_CLOSE_TAG.is_match(line)) {
+        Some(None)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::comment::CommentErrorKind;
+    use crate::util::TempDir;
+
+    fn matcher() -> Matcher {
+        Matcher {
+            enable_code_comment_tweak: false,
+            code_comment_header: String::from("# "),
+            code_comment_syntax: CodeCommentSyntax::default(),
+            keep_markdown_comment: false,
+            markdown_comment_begin: String::from("((("),
+            markdown_comment_end: String::from(")))"),
+            similar_threshold: 0.5,
+        }
+    }
 
     #[test]
     fn test_get_mismatch_lines_match() {
@@ -428,10 +845,61 @@ mod test {
         ccc
             "##;
 
-        let (ret, _) = Matcher::get_mismatch_lines(source, target);
+        let (ret, _) = matcher().get_mismatch_lines(source, target);
         assert_eq!(ret.len(), 0);
     }
 
+    #[test]
+    fn test_get_mismatch_lines_one_line_comment() {
+        let source = r##"
+        aaa
+            "##;
+        let target = r##"
+        aaa
+        <!-- transcheck-disable-next-line emphasis -->
+        ddd
+            "##;
+
+        let (ret, right) = matcher().get_mismatch_lines(source, target);
+        assert_eq!(ret.len(), 0);
+        assert!(right.iter().all(|x| !x.html_comment));
+    }
+
+    #[test]
+    fn test_get_mismatch_lines_inline_comment() {
+        let source = r##"
+aaa
+bbb
+ccc
+ddd
+            "##;
+        let target = r##"
+<!-- aaa
+bbb -->
+あああ <!-- ccc -->
+<!-- ddd -->
+eee
+            "##;
+
+        let (ret, right) = matcher().get_mismatch_lines(source, target);
+        assert_eq!(ret.len(), 0);
+        assert_eq!(right.len(), 1);
+        assert_eq!(right[0].content, "eee");
+
+        let target = r##"
+<!-- aaa
+bbc -->
+<!-- ccc --> あああ
+<!-- ddd -->
+            "##;
+
+        let (ret, _) = matcher().get_mismatch_lines(source, target);
+        assert_eq!(ret.len(), 1);
+        assert_eq!(ret[0].1.len(), 1);
+        assert_eq!(ret[0].1[0].content, "bbc");
+        assert!(ret[0].1[0].html_comment);
+    }
+
     #[test]
     fn test_get_mismatch_lines_diff() {
         let source = r##"
@@ -447,7 +915,7 @@ mod test {
         ccc
             "##;
 
-        let (ret, _) = Matcher::get_mismatch_lines(source, target);
+        let (ret, _) = matcher().get_mismatch_lines(source, target);
         assert_eq!(ret.len(), 1);
         assert_eq!(ret[0].0.len(), 1);
         assert_eq!(ret[0].0[0].number, 3);
@@ -461,6 +929,35 @@ mod test {
         assert_eq!(ret[0].1[2].content, "        eee");
     }
 
+    #[test]
+    fn test_get_mismatch_lines_code_comment() {
+        let source = r##"
+```python
+x = 1
+```
+```
+x = 1
+```
+"##;
+        let target = r##"
+```python
+# いち
+x = 2
+```
+```
+# いち
+x = 2
+```
+"##;
+
+        let (ret, _) = matcher().get_mismatch_lines(source, target);
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[0].1[0].content, "# いち");
+        assert!(!ret[0].1[0].code_not_comment);
+        assert!(ret[0].1[1].code_not_comment);
+        assert!(ret[1].1[0].code_not_comment);
+    }
+
     #[test]
     fn test_keep_original_line() {
         let source = r##"
@@ -474,15 +971,158 @@ aaa
 
             "##;
 
-        let (ret, _) = Matcher::get_mismatch_lines(source, target);
+        let (ret, _) = matcher().get_mismatch_lines(source, target);
         assert_eq!(ret.len(), 0);
     }
 
+    #[test]
+    fn test_get_line_contexts() {
+        let target = r##"---
+title: Hello
+---
+<!--
+Hello
+-->
+こんにちは
+<!-- transcheck-disable -->
+```rust
+let x = 1;
+```
+<div class="warning">
+注意
+</div>
+
+<kbd>Ctrl</kbd> を押します(注意)
+<https://example.com>
+<span>
+
+<span>
+テキスト
+
+<script>
+let x;
+
+</script>
+テキスト
+"##;
+        use LineContext::*;
+        let ret = Matcher::get_line_contexts(target);
+        assert_eq!(
+            ret,
+            vec![
+                FrontMatter,
+                FrontMatter,
+                FrontMatter,
+                HtmlComment,
+                HtmlComment,
+                HtmlComment,
+                Text,
+                HtmlComment,
+                CodeBlock,
+                CodeBlock,
+                CodeBlock,
+                HtmlBlock,
+                HtmlBlock,
+                HtmlBlock,
+                Text,
+                Text,
+                Text,
+                Text,
+                Text,
+                HtmlBlock,
+                HtmlBlock,
+                Text,
+                HtmlBlock,
+                HtmlBlock,
+                HtmlBlock,
+                HtmlBlock,
+                Text,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_translation_pairs() {
+        let target = r##"
+<!--
+# Apple
+
+Apple is red.
+-->
+# りんご
+
+りんごは赤い。
+
+```rust
+let a = 1;
+```
+
+<!--
+Orange
+Peach
+-->
+オレンジ
+            "##;
+
+        let ret = Matcher::get_translation_pairs(target);
+        assert_eq!(ret.len(), 3);
+        assert_eq!(ret[0].original[0].content, "# Apple");
+        assert_eq!(ret[0].translated[0].content, "# りんご");
+        assert_eq!(ret[1].original[0].content, "Apple is red.");
+        assert_eq!(ret[1].translated[0].number, 9);
+        assert_eq!(ret[2].original.len(), 2);
+        assert_eq!(ret[2].translated[0].content, "オレンジ");
+    }
+
+    #[test]
+    fn test_get_translation_pairs_inline_comment() {
+        let target = r##"
+<!-- Apple -->
+りんご
+
+<!-- Orange
+Peach -->
+オレンジ
+            "##;
+
+        let ret = Matcher::get_translation_pairs(target);
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[0].original[0].content, "Apple");
+        assert_eq!(ret[0].translated[0].content, "りんご");
+        assert_eq!(ret[1].original.len(), 2);
+        assert_eq!(ret[1].original[1].content, "Peach");
+        assert_eq!(ret[1].translated[0].content, "オレンジ");
+    }
+
+    #[test]
+    fn test_revert_code_comment() {
+        let matcher = Matcher {
+            enable_code_comment_tweak: true,
+            code_comment_header: String::from("# // "),
+            code_comment_syntax: CodeCommentSyntax::default(),
+            keep_markdown_comment: false,
+            markdown_comment_begin: String::from("((("),
+            markdown_comment_end: String::from(")))"),
+            similar_threshold: 0.5,
+        };
+        let target = "# // aaa\n```rust\n# // let a = b;\nlet a = c;\n```\n";
+        let ret = matcher.revert_code_comment(target);
+        assert_eq!(ret, "# // aaa\n```rust\nlet a = b;\nlet a = c;\n```\n");
+
+        let target = "~~~~\n```\n# // a\n~~~~\n\n    # // let a = b;\n    let a = c;\n";
+        let ret = matcher.revert_code_comment(target);
+        assert_eq!(
+            ret,
+            "~~~~\n```\na\n~~~~\n\n    let a = b;\n    let a = c;\n"
+        );
+    }
+
     #[test]
     fn test_check_dir() {
         let matcher = Matcher {
             enable_code_comment_tweak: true,
             code_comment_header: String::from("# "),
+            code_comment_syntax: CodeCommentSyntax::default(),
             keep_markdown_comment: false,
             markdown_comment_begin: String::from("((("),
             markdown_comment_end: String::from(")))"),
@@ -498,6 +1138,7 @@ aaa
         ret.sort_by_key(|x| match x {
             Mismatch::MismatchLines(x) => x.source_path.clone(),
             Mismatch::MissingFile(x) => x.source_path.clone(),
+            Mismatch::MalformedComment(x) => x.source_path.clone(),
         });
         assert_eq!(ret.len(), 6);
         assert!(
@@ -525,6 +1166,7 @@ aaa
         let matcher = Matcher {
             enable_code_comment_tweak: true,
             code_comment_header: String::from("# "),
+            code_comment_syntax: CodeCommentSyntax::default(),
             keep_markdown_comment: true,
             markdown_comment_begin: String::from("((("),
             markdown_comment_end: String::from(")))"),
@@ -546,6 +1188,7 @@ aaa
         ret.sort_by_key(|x| match x {
             Mismatch::MismatchLines(x) => x.source_path.clone(),
             Mismatch::MissingFile(x) => x.source_path.clone(),
+            Mismatch::MalformedComment(x) => x.source_path.clone(),
         });
         assert_eq!(ret.len(), 4);
         assert!(
@@ -561,4 +1204,28 @@ aaa
             matches!(&ret[3], Mismatch::MissingFile(x) if x.source_path.file_name().unwrap() == "04_missing_file.md")
         );
     }
+
+    #[test]
+    fn test_remove_markdown_comment() {
+        let matcher = matcher();
+        let ret = matcher
+            .remove_markdown_comment("a --> b\n<!-- c -->d\n")
+            .unwrap();
+        assert_eq!(ret, "a --> b\nd\n");
+        assert!(matcher.remove_markdown_comment("a\n<!-- b\n").is_err());
+    }
+
+    #[test]
+    fn test_check_file_malformed_comment() {
+        let matcher = matcher();
+        let dir = TempDir::new();
+        let source = dir.write("source.md", "aaa\n");
+        let target = dir.write("target.md", "<!--\naaa\n-->\nあああ\n-->\n");
+
+        let (ret, target_only) = matcher.check_file(&source, &target).unwrap();
+        assert!(target_only.is_none());
+        assert!(
+            matches!(&ret, Mismatch::MalformedComment(x) if x.in_target && x.error.kind == CommentErrorKind::Misordered && x.error.line == 5 && x.error.column == 1)
+        );
+    }
 }
@@ -1,42 +1,114 @@
+use crate::comment::CommentErrorKind;
+use crate::config::{QuoteStyle, Severity};
 use crate::linter::{LintError, LintErrorKind};
-use crate::matcher::{Line, Mismatch, MismatchLines, MissingFile};
+use crate::matcher::{Line, MalformedComment, Mismatch, MismatchLines, MissingFile};
 use crate::util::{combine_line, CombinedLine};
 use anyhow::Error;
-use console::style;
+use console::{style, StyledObject};
+use std::collections::BTreeMap;
+
+/// Number of reported items in each stage, `None` if the stage is not executed
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+    pub fixes: Option<usize>,
+    pub mismatches: Option<usize>,
+    pub lint_errors: Option<usize>,
+}
 
 pub struct Printer {
+    /// Print also the files without mismatches
     pub verbose: bool,
+    pub severity: BTreeMap<String, Severity>,
 }
 
 impl Printer {
-    pub fn print_mismatch(&self, mismatches: &[Mismatch]) -> Result<bool, Error> {
-        let mut ret = true;
+    /// Print mismatches and return the number of errors
+    pub fn print_mismatch(&self, mismatches: &[Mismatch]) -> Result<usize, Error> {
+        let mut ret = 0;
         for mismatch in mismatches {
             match mismatch {
                 Mismatch::MissingFile(x) => {
-                    ret &= self.print_missing_file(x)?;
+                    ret += self.print_missing_file(x)?;
                 }
                 Mismatch::MismatchLines(x) => {
-                    ret &= self.print_mismatch_lines(x)?;
+                    ret += self.print_mismatch_lines(x)?;
+                }
+                Mismatch::MalformedComment(x) => {
+                    ret += self.print_malformed_comment(x)?;
                 }
             }
         }
         Ok(ret)
     }
 
-    pub fn print_lint(&self, lint_errors: &[LintError]) -> Result<bool, Error> {
-        let mut ret = true;
+    /// Print only malformed markdown comments and return the number of errors
+    pub fn print_malformed_comments(&self, mismatches: &[Mismatch]) -> Result<usize, Error> {
+        let mut ret = 0;
+        for mismatch in mismatches {
+            if let Mismatch::MalformedComment(x) = mismatch {
+                ret += self.print_malformed_comment(x)?;
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Print lint errors and return the number of errors
+    pub fn print_lint(&self, lint_errors: &[LintError]) -> Result<usize, Error> {
+        let mut ret = 0;
+        for error in lint_errors {
+            self.print_lint_error(error, " target --> ");
+            if error.severity == Severity::Error {
+                ret += 1;
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Print lint errors of source files and return the number of errors
+    pub fn print_source_lint(&self, lint_errors: &[LintError]) -> Result<usize, Error> {
+        let mut ret = 0;
         for error in lint_errors {
-            self.print_lint_error(error);
-            ret = false;
+            self.print_lint_error(error, " source --> ");
+            if error.severity == Severity::Error {
+                ret += 1;
+            }
         }
         Ok(ret)
     }
 
-    fn print_missing_file(&self, missing: &MissingFile) -> Result<bool, Error> {
+    pub fn print_summary(&self, summary: &Summary) {
+        let mut items = Vec::new();
+        if let Some(x) = summary.fixes {
+            items.push(format!("{} fix{}", x, if x == 1 { "" } else { "es" }));
+        }
+        if let Some(x) = summary.mismatches {
+            items.push(format!("{} mismatch{}", x, if x == 1 { "" } else { "es" }));
+        }
+        if let Some(x) = summary.lint_errors {
+            items.push(format!("{} lint error{}", x, if x == 1 { "" } else { "s" }));
+        }
+
+        println!(
+            "\n{}{}",
+            style("Summary").white().bold(),
+            style(format!(": {}", items.join(", "))).white().bold()
+        );
+    }
+
+    /// Severity of the mismatch kind `id`
+    fn severity(&self, id: &str) -> Severity {
+        self.severity.get(id).copied().unwrap_or_default()
+    }
+
+    fn print_missing_file(&self, missing: &MissingFile) -> Result<usize, Error> {
+        let severity = self.severity("missing-file");
+        if severity == Severity::Off {
+            return Ok(0);
+        }
+
         println!(
             "\n{}{}",
-            style("Error").red().bold(),
+            header(severity),
             style(": target path is not found").white().bold()
         );
         println!(
@@ -47,29 +119,110 @@ impl Printer {
             "    target path: {}\n",
             style(missing.target_path.to_string_lossy()).white()
         );
-        Ok(false)
+        Ok(usize::from(severity == Severity::Error))
+    }
+
+    fn print_malformed_comment(&self, malformed: &MalformedComment) -> Result<usize, Error> {
+        let severity = self.severity("malformed-comment");
+        if severity == Severity::Off {
+            return Ok(0);
+        }
+
+        let error = &malformed.error;
+        let message = match error.kind {
+            CommentErrorKind::Unterminated => "markdown comment is not closed",
+            CommentErrorKind::Misordered => "'-->' is found without the preceding '<!--'",
+        };
+        println!(
+            "\n{}{}",
+            header(severity),
+            style(format!(": {}", message)).white().bold()
+        );
+        println!(
+            "{}{}",
+            style(if malformed.in_target {
+                " target --> "
+            } else {
+                " source --> "
+            })
+            .blue()
+            .bold(),
+            style(format!(
+                "{}:{}:{}",
+                malformed.path().to_string_lossy(),
+                error.line,
+                error.column
+            ))
+            .white()
+        );
+
+        let number = format!("{}", error.line);
+        let number_space = " ".repeat(number.len());
+        let before_mark: String = error.content.chars().take(error.column - 1).collect();
+        let before_mark = console::measure_text_width(&before_mark);
+        let mark = match error.kind {
+            CommentErrorKind::Unterminated => 4,
+            CommentErrorKind::Misordered => 3,
+        };
+
+        println!("{}", style(format!("{} |", number_space)).blue().bold());
+        println!(
+            "{}{}",
+            style(format!("{} | ", number)).blue().bold(),
+            style(&error.content).white(),
+        );
+        println!(
+            "{}{}{}",
+            style(format!("{} | ", number_space)).blue().bold(),
+            " ".repeat(before_mark),
+            style("^".repeat(mark)).yellow().bold(),
+        );
+        println!("{}", style(format!("{} |", number_space)).blue().bold());
+        Ok(usize::from(severity == Severity::Error))
     }
 
-    fn print_mismatch_lines(&self, mismatch: &MismatchLines) -> Result<bool, Error> {
+    fn print_mismatch_lines(&self, mismatch: &MismatchLines) -> Result<usize, Error> {
         if mismatch.lines.is_empty() {
-            Ok(true)
+            if self.verbose {
+                println!(
+                    "{}{}",
+                    style("Checked").green().bold(),
+                    style(format!(
+                        ": {} --> {}",
+                        mismatch.source_path.to_string_lossy(),
+                        mismatch.target_path.to_string_lossy()
+                    ))
+                    .white()
+                );
+            }
+            Ok(0)
         } else {
             let lines = combine_line(mismatch);
 
+            let mut ret = 0;
             for line in &lines {
-                match line {
-                    CombinedLine::Modified((x, y)) => {
-                        self.print_modified_line(mismatch, x, y)?;
+                let severity = self.severity(match line {
+                    CombinedLine::Modified(_) => "modified",
+                    CombinedLine::Missing(_) => "missing",
+                    CombinedLine::Garbage(_) => "garbage",
+                });
+                match (line, severity) {
+                    (_, Severity::Off) => continue,
+                    (CombinedLine::Modified((x, y)), _) => {
+                        self.print_modified_line(mismatch, x, y, severity)?;
                     }
-                    CombinedLine::Missing(x) => {
-                        self.print_missing_line(mismatch, x.as_slice())?;
+                    (CombinedLine::Missing(x), _) => {
+                        self.print_missing_line(mismatch, x.as_slice(), severity)?;
                     }
-                    CombinedLine::Garbage(x) => {
-                        self.print_garbage_line(mismatch, x.as_slice())?;
+                    (CombinedLine::Garbage(x), _) => {
+                        self.print_garbage_line(mismatch, x.as_slice(), severity)?;
                     }
                 }
+                if severity == Severity::Error {
+                    ret += 1;
+                }
             }
-            Ok(false)
+            Ok(ret)
         }
     }
 
@@ -78,6 +231,7 @@ impl Printer {
         mismatch: &MismatchLines,
         source: &Line,
         target: &Line,
+        severity: Severity,
     ) -> Result<(), Error> {
         let mut source_anno = String::from("");
         let mut target_anno = String::from("");
@@ -101,7 +255,7 @@ impl Printer {
 
         println!(
             "\n{}{}",
-            style("Error").red().bold(),
+            header(severity),
             style(": source line has been modified").white().bold()
         );
         println!(
@@ -156,10 +310,15 @@ impl Printer {
         Ok(())
     }
 
-    fn print_missing_line(&self, mismatch: &MismatchLines, sources: &[&Line]) -> Result<(), Error> {
+    fn print_missing_line(
+        &self,
+        mismatch: &MismatchLines,
+        sources: &[&Line],
+        severity: Severity,
+    ) -> Result<(), Error> {
         println!(
             "\n{}{}",
-            style("Error").red().bold(),
+            header(severity),
             style(": lines has been inserted to the source file")
                 .white()
                 .bold()
@@ -213,10 +372,15 @@ impl Printer {
         Ok(())
     }
 
-    fn print_garbage_line(&self, mismatch: &MismatchLines, targets: &[&Line]) -> Result<(), Error> {
+    fn print_garbage_line(
+        &self,
+        mismatch: &MismatchLines,
+        targets: &[&Line],
+        severity: Severity,
+    ) -> Result<(), Error> {
         println!(
             "\n{}{}",
-            style("Error").red().bold(),
+            header(severity),
             style(": lines has been removed from the source file")
                 .white()
                 .bold()
@@ -258,22 +422,154 @@ impl Printer {
         Ok(())
     }
 
-    fn print_lint_error(&self, error: &LintError) {
-        let message = match error.kind {
-            LintErrorKind::EmphasisWithoutSpace => "emphasis must have spaces before and after it",
-            LintErrorKind::EmphasisMismatch => "emphasis token is mismatched",
-            LintErrorKind::HalfParenWithNonAscii => "non-ascii string must have full-width paren",
-            LintErrorKind::FullParenWithoutNonAscii => "ascii string must have half-width paren",
+    fn print_lint_error(&self, error: &LintError, arrow: &str) {
+        let message = match &error.kind {
+            LintErrorKind::EmphasisWithoutSpace => {
+                String::from("emphasis must have spaces before and after it")
+            }
+            LintErrorKind::EmphasisMismatch => String::from("emphasis token is mismatched"),
+            LintErrorKind::HalfParenWithNonAscii => {
+                String::from("non-ascii string must have full-width paren")
+            }
+            LintErrorKind::FullParenWithoutNonAscii => {
+                String::from("ascii string must have half-width paren")
+            }
+            LintErrorKind::Custom { id, message } => format!("{} ({})", message, id),
+            LintErrorKind::GlossaryMismatch {
+                source,
+                translations,
+            } => format!(
+                "'{}' must be translated as '{}'",
+                source,
+                translations.join("' or '")
+            ),
+            LintErrorKind::CodeSpanNotPreserved { text } => {
+                format!("inline code {} is not found in the translation", text)
+            }
+            LintErrorKind::UrlNotPreserved { text } => {
+                format!("link '{}' is not found in the translation", text)
+            }
+            LintErrorKind::HeadingLevelMismatch {
+                original,
+                translated,
+            } => match (original, translated) {
+                (Some(x), Some(y)) => format!(
+                    "heading level must be {} ( {} ) but {} ( {} )",
+                    x,
+                    "#".repeat(*x),
+                    y,
+                    "#".repeat(*y)
+                ),
+                (Some(x), None) => format!(
+                    "heading level {} ( {} ) is not found in the translation",
+                    x,
+                    "#".repeat(*x)
+                ),
+                (None, Some(y)) => format!(
+                    "heading level {} ( {} ) is not found in the original",
+                    y,
+                    "#".repeat(*y)
+                ),
+                (None, None) => unreachable!(),
+            },
+            LintErrorKind::HeadingAnchorNotPreserved { text } => {
+                format!("heading anchor '{}' is not found in the translation", text)
+            }
+            LintErrorKind::LinkTargetNotFound { text } => {
+                format!("link target '{}' is not found", text)
+            }
+            LintErrorKind::LinkAnchorNotFound { text } => {
+                format!("heading anchor of link '{}' is not found", text)
+            }
+            LintErrorKind::PlaceholderNotPreserved { text } => {
+                format!("placeholder '{}' is not found in the translation", text)
+            }
+            LintErrorKind::NumberNotPreserved { text } => {
+                format!("number '{}' is not found in the translation", text)
+            }
+            LintErrorKind::CodeBlockInfoMismatch {
+                original,
+                translated,
+            } => format!(
+                "code block info '{}' differs from the original '{}'",
+                translated, original
+            ),
+            LintErrorKind::CodeBlockMismatch { original } => {
+                format!("code differs from the original '{}'", original)
+            }
+            LintErrorKind::CodeBlockMissing { original } => {
+                format!("code block at {} is not found in the translation", original)
+            }
+            LintErrorKind::CodeBlockGarbage => {
+                String::from("code block is not found in the original")
+            }
+            LintErrorKind::ListMismatch {
+                original,
+                translated,
+            } => {
+                if original.len() == translated.len() {
+                    String::from("nesting of list items differs from the original")
+                } else {
+                    format!(
+                        "list has {} items, but the original has {} items",
+                        translated.len(),
+                        original.len()
+                    )
+                }
+            }
+            LintErrorKind::TableMismatch {
+                original,
+                translated,
+            } => format!(
+                "table columns [{}] differ from the original [{}]",
+                translated.join(", "),
+                original.join(", ")
+            ),
+            LintErrorKind::BlockquoteMismatch {
+                original,
+                translated,
+            } => format!(
+                "blockquote depth {} differs from the original {}",
+                translated, original
+            ),
+            LintErrorKind::FootnoteNotPreserved { text } => {
+                format!("footnote '{}' is not found in the translation", text)
+            }
+            LintErrorKind::Untranslated => String::from("line seems to be untranslated"),
+            LintErrorKind::CjkSpaceRequired => {
+                String::from("space is required between CJK and alphanumeric characters")
+            }
+            LintErrorKind::CjkSpaceForbidden => {
+                String::from("space is forbidden between CJK and alphanumeric characters")
+            }
+            LintErrorKind::HalfPunctuationWithCjk => {
+                String::from("half-width punctuation follows CJK characters")
+            }
+            LintErrorKind::QuoteStyleMismatch { style } => match style {
+                QuoteStyle::Corner => String::from("quote should be corner brackets (「」)"),
+                QuoteStyle::Curly => String::from("quote should be curly quotes (“”)"),
+            },
+            LintErrorKind::FullWidthAlnum => {
+                String::from("full-width alphanumeric characters are used")
+            }
+            LintErrorKind::InlineComment => {
+                String::from("markdown comment should occupy whole lines")
+            }
+            LintErrorKind::UnterminatedComment => String::from("markdown comment is not closed"),
+            LintErrorKind::MisorderedComment => {
+                String::from("'-->' is found without the preceding '<!--'")
+            }
+            LintErrorKind::TrailingWhitespace => String::from("line has trailing whitespace"),
         };
 
         println!(
             "\n{}{}",
-            style("Error").red().bold(),
+            header(error.severity),
             style(format!(": {}", message)).white().bold()
         );
         println!(
             "{}{}",
-            style(" target --> ").blue().bold(),
+            style(arrow).blue().bold(),
             style(format!(
                 "{}:{}",
                 error.path.to_string_lossy(),
@@ -285,12 +581,8 @@ impl Printer {
         let number = format!("{}", error.line.number);
         let number_space = " ".repeat(number.len());
 
-        let before_mark =
-            console::measure_text_width(&format!("{}", &error.line.content[..error.start]));
-        let mark = console::measure_text_width(&format!(
-            "{}",
-            &error.line.content[error.start..error.end]
-        ));
+        let before_mark = console::measure_text_width(&error.line.content[..error.start]);
+        let mark = console::measure_text_width(&error.line.content[error.start..error.end]);
 
         println!("{}", style(format!("{} |", number_space)).blue().bold());
         println!(
@@ -307,3 +599,10 @@ impl Printer {
         println!("{}", style(format!("{} |", number_space)).blue().bold());
     }
 }
+
+fn header(severity: Severity) -> StyledObject<&'static str> {
+    match severity {
+        Severity::Warn => style("Warning").yellow().bold(),
+        _ => style("Error").red().bold(),
+    }
+}
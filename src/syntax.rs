@@ -0,0 +1,244 @@
+use crate::fence::CodeLine;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Comment syntax of a language in code blocks
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommentSyntax {
+    /// Beginning of line comments like `//`
+    #[serde(default)]
+    pub line: Vec<String>,
+    /// Beginning and end of block comments like `["/*", "*/"]`
+    #[serde(default)]
+    pub block: Vec<(String, String)>,
+    /// Header to hide the original lines, `code_comment_header` is used if not specified
+    #[serde(default)]
+    pub header: Option<String>,
+}
+
+impl CommentSyntax {
+    /// Header of the language or `default`
+    pub fn header_or<'a>(&'a self, default: &'a str) -> &'a str {
+        self.header.as_deref().unwrap_or(default)
+    }
+
+    fn new(line: &[&str], block: &[(&str, &str)]) -> Self {
+        CommentSyntax {
+            line: line.iter().copied().map(String::from).collect(),
+            block: block
+                .iter()
+                .map(|(x, y)| (String::from(*x), String::from(*y)))
+                .collect(),
+            header: None,
+        }
+    }
+}
+
+/// Comment syntax of each language which is specified by the info string of code blocks
+#[derive(Clone, Debug)]
+pub struct CodeCommentSyntax {
+    languages: BTreeMap<String, CommentSyntax>,
+    fallback: CommentSyntax,
+}
+
+impl Default for CodeCommentSyntax {
+    fn default() -> Self {
+        let c_like = CommentSyntax::new(&["//"], &[("/*", "*/")]);
+        let hash = CommentSyntax::new(&["#"], &[]);
+        let dash = CommentSyntax::new(&["--"], &[]);
+        let markup = CommentSyntax::new(&[], &[("<!--", "-->")]);
+        let semicolon = CommentSyntax::new(&[";"], &[]);
+
+        let mut languages = BTreeMap::new();
+        let builtins = [
+            (&c_like, "c cpp c++ cs csharp dart go java javascript js kotlin rust scala swift ts typescript zig"),
+            (&hash, "bash dockerfile elixir julia makefile nix perl powershell py python r rb ruby sh shell toml yaml yml zsh"),
+            (&dash, "ada elm haskell hs lua sql"),
+            (&markup, "html markdown md svg xml"),
+            (&semicolon, "asm clojure ini lisp scheme"),
+        ];
+        for (syntax, names) in builtins {
+            for name in names.split(' ') {
+                languages.insert(String::from(name), syntax.clone());
+            }
+        }
+
+        CodeCommentSyntax {
+            languages,
+            fallback: CommentSyntax::new(&["//"], &[]),
+        }
+    }
+}
+
+impl CodeCommentSyntax {
+    /// Built-in syntax overridden by `languages`
+    pub fn new(languages: &BTreeMap<String, CommentSyntax>) -> Self {
+        let mut ret = CodeCommentSyntax::default();
+        for (name, syntax) in languages {
+            ret.languages.insert(name.to_lowercase(), syntax.clone());
+        }
+        ret
+    }
+
+    /// Get the syntax from the info string like `rust,ignore`
+    ///
+    /// Unknown languages and indented code blocks fall back to `//` comments.
+    pub fn get(&self, info: &str) -> &CommentSyntax {
+        let name = info
+            .trim_start_matches(['{', '.'])
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '}')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        self.languages.get(&name).unwrap_or(&self.fallback)
+    }
+
+    /// Get the syntax of each line, `None` if the line is not code
+    pub fn lines(&self, codes: &[CodeLine]) -> Vec<Option<&CommentSyntax>> {
+        let mut ret = Vec::new();
+        let mut info = "";
+        for code in codes {
+            match code {
+                CodeLine::Open(x) => {
+                    info = x;
+                    ret.push(None);
+                }
+                CodeLine::Fenced => ret.push(Some(self.get(info))),
+                CodeLine::Indented => ret.push(Some(&self.fallback)),
+                CodeLine::Close | CodeLine::Text => ret.push(None),
+            }
+        }
+        ret
+    }
+
+    /// Check whether each line is code without comments
+    pub fn code_not_comment(&self, text: &str, codes: &[CodeLine]) -> Vec<bool> {
+        let mut ret = Vec::new();
+        let mut scanner: Option<CommentScanner> = None;
+        for (line, syntax) in text.lines().zip(self.lines(codes)) {
+            match syntax {
+                Some(syntax) => {
+                    let scanner = scanner.get_or_insert_with(|| CommentScanner::new(syntax));
+                    ret.push(!scanner.strip(line).1);
+                }
+                None => {
+                    scanner = None;
+                    ret.push(false);
+                }
+            }
+        }
+        ret
+    }
+}
+
+/// Splitter of code and comments keeping block comments across lines
+#[derive(Clone, Debug)]
+pub struct CommentScanner<'a> {
+    syntax: &'a CommentSyntax,
+    block_end: Option<&'a str>,
+}
+
+impl<'a> CommentScanner<'a> {
+    pub fn new(syntax: &'a CommentSyntax) -> Self {
+        CommentScanner {
+            syntax,
+            block_end: None,
+        }
+    }
+
+    pub fn syntax(&self) -> &'a CommentSyntax {
+        self.syntax
+    }
+
+    /// Remove comments from `line` and return the code and whether the line has comments
+    pub fn strip(&mut self, line: &str) -> (String, bool) {
+        let mut code = String::new();
+        let mut comment = false;
+        let mut pos = 0;
+        loop {
+            if let Some(end) = self.block_end {
+                comment = true;
+                match line[pos..].find(end) {
+                    Some(i) => {
+                        pos += i + end.len();
+                        self.block_end = None;
+                    }
+                    None => return (code, comment),
+                }
+            }
+
+            let line_comment = self
+                .syntax
+                .line
+                .iter()
+                .filter_map(|x| line[pos..].find(x.as_str()))
+                .min();
+            let block_comment = self
+                .syntax
+                .block
+                .iter()
+                .filter_map(|(x, y)| line[pos..].find(x.as_str()).map(|i| (i, x, y)))
+                .min_by_key(|x| x.0);
+
+            match (line_comment, block_comment) {
+                (Some(i), x) if x.is_none_or(|x| i <= x.0) => {
+                    code.push_str(&line[pos..pos + i]);
+                    return (code, true);
+                }
+                (_, Some((i, begin, end))) => {
+                    code.push_str(&line[pos..pos + i]);
+                    pos += i + begin.len();
+                    self.block_end = Some(end);
+                }
+                _ => {
+                    code.push_str(&line[pos..]);
+                    return (code, comment);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fence;
+
+    #[test]
+    fn test_code_not_comment() {
+        let text = r##"```python
+x = 1  # one
+y = 2
+```
+```rust,ignore
+let x = 1; /* one
+two */ let y = 2;
+let z = 3;
+```
+"##;
+        let syntax = CodeCommentSyntax::default();
+        let ret = syntax.code_not_comment(text, &fence::track(text));
+        assert_eq!(
+            ret,
+            vec![false, false, true, false, false, false, false, true, false]
+        );
+    }
+
+    #[test]
+    fn test_strip() {
+        let mut languages = BTreeMap::new();
+        languages.insert(
+            String::from("SQL"),
+            CommentSyntax::new(&["--"], &[("/*", "*/")]),
+        );
+        let syntax = CodeCommentSyntax::new(&languages);
+        let mut scanner = CommentScanner::new(syntax.get("sql"));
+        assert_eq!(
+            scanner.strip("SELECT /* all */ * FROM t -- comment"),
+            (String::from("SELECT  * FROM t "), true)
+        );
+        let mut scanner = CommentScanner::new(syntax.get("text"));
+        assert_eq!(scanner.strip("a # b // c"), (String::from("a # b "), true));
+    }
+}
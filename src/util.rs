@@ -1,6 +1,8 @@
 use crate::matcher::{Line, MismatchLine, MismatchLines};
 use anyhow::Error;
 use console::style;
+#[cfg(test)]
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub enum CombinedLine<'a> {
@@ -9,6 +11,49 @@ pub enum CombinedLine<'a> {
     Garbage(Vec<&'a Line>),
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextFormat {
+    pub bom: bool,
+    pub crlf: bool,
+    pub trailing_new_line: bool,
+}
+
+impl TextFormat {
+    pub fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        TextFormat {
+            bom: text.starts_with('\u{feff}'),
+            crlf: crlf > lf,
+            trailing_new_line: text.ends_with('\n'),
+        }
+    }
+
+    /// Split `text` into lines without BOM and line endings
+    pub fn lines<'a>(&self, text: &'a str) -> std::str::Lines<'a> {
+        text.strip_prefix('\u{feff}').unwrap_or(text).lines()
+    }
+
+    /// Join `lines` with the detected BOM, line ending and trailing new line
+    pub fn join<T: AsRef<str>>(&self, lines: &[T]) -> String {
+        let new_line = if self.crlf { "\r\n" } else { "\n" };
+        let mut ret = String::new();
+        if self.bom {
+            ret.push('\u{feff}');
+        }
+        for (i, line) in lines.iter().enumerate() {
+            if i != 0 {
+                ret.push_str(new_line);
+            }
+            ret.push_str(line.as_ref());
+        }
+        if self.trailing_new_line && !lines.is_empty() {
+            ret.push_str(new_line);
+        }
+        ret
+    }
+}
+
 pub fn combine_line<'a>(mismatch: &'a MismatchLines) -> Vec<CombinedLine<'a>> {
     let mut lines = Vec::new();
     let mut source_neighbor = Vec::new();
@@ -79,7 +124,7 @@ pub fn print_error(x: Error) {
     );
 
     for x in cause {
-        let _ = eprintln!(
+        eprintln!(
             "  {}{}",
             console::style("caused by: ").white().bold(),
             console::style(x).white()
@@ -96,10 +141,51 @@ pub fn print_warning(x: Error) {
     );
 
     for x in cause {
-        let _ = eprintln!(
+        eprintln!(
             "  {}{}",
             console::style("caused by: ").white().bold(),
             console::style(x).white()
         );
     }
 }
+
+/// Temporary directory for tests which is unique in parallel runs and removed on drop
+#[cfg(test)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new() -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "mdbook-transcheck-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write `text` to `name` in the directory and return the path
+    pub fn write(&self, name: &str, text: &str) -> PathBuf {
+        let path = self.path.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}